@@ -0,0 +1,334 @@
+// [Binary Lambda Calculus](https://tromp.github.io/cl/Binary_lambda_calculus.html)
+//
+// 00 M     is λM
+// 01 M N   is (M N)
+// 1^n+1 0  is the variable n
+//
+// The universal machine reads a program from the start of the stream and applies it to the
+// rest of the stream, encoded as a list. A bit 0 is `a:b:a`, a bit 1 is `a:b:b`, a list cell
+// is `a:(a head tail)` and the end of the list is `a:b:b`.
+
+use crate::{Expr, nbe};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// BLC, the stream is made of the characters `0` and `1`.
+    Bits,
+    /// BLC8, the stream is made of bytes, every byte is a list of 8 bits.
+    Bytes,
+}
+
+/// Parses one program from the start of `bits`, returns the program and how many bits was used.
+pub fn parse(bits: &[bool]) -> Option<(Expr, usize)> {
+    let mut i = 0;
+    let expr = _parse(bits, &mut i, 0)?;
+    Some((expr, i))
+}
+
+fn _parse(bits: &[bool], i: &mut usize, depth: u32) -> Option<Expr> {
+    let first = *bits.get(*i)?;
+    *i += 1;
+
+    if first {
+        let mut id = 0;
+        while *bits.get(*i)? {
+            *i += 1;
+            id += 1;
+        }
+        *i += 1;
+
+        if id >= depth {
+            eprintln!("Variable {id} is not bound at bit {}", *i - 1);
+            return None;
+        }
+
+        return Some(Expr::Relative(id));
+    }
+
+    let second = *bits.get(*i)?;
+    *i += 1;
+
+    if !second {
+        let body = _parse(bits, i, depth + 1)?;
+        return Some(Expr::Def(Box::new(body)));
+    }
+
+    let function = _parse(bits, i, depth)?;
+    let argument = _parse(bits, i, depth)?;
+
+    Some(match function {
+        Expr::Group(mut exprs) if exprs.len() > 1 => {
+            exprs.push(argument);
+            Expr::Group(exprs)
+        }
+        function => Expr::Group(vec![function, argument]),
+    })
+}
+
+/// Encodes the expression as BLC, labels and empty groups cannot be encoded.
+pub fn encode(expr: &Expr) -> Option<Vec<bool>> {
+    let mut out = Vec::default();
+    _encode(expr, &mut out)?;
+    Some(out)
+}
+
+fn _encode(expr: &Expr, out: &mut Vec<bool>) -> Option<()> {
+    match expr {
+        Expr::Group(exprs) => {
+            let (first, rest) = exprs.split_first()?;
+            for _ in rest {
+                out.push(false);
+                out.push(true);
+            }
+            _encode(first, out)?;
+            for expr in rest {
+                _encode(expr, out)?;
+            }
+        }
+        Expr::Def(expr) => {
+            out.push(false);
+            out.push(false);
+            _encode(expr, out)?;
+        }
+        Expr::Relative(id) => {
+            for _ in 0..=*id {
+                out.push(true);
+            }
+            out.push(false);
+        }
+        Expr::Label(name) => {
+            eprintln!("Label `'{name}` cannot be encoded as BLC");
+            return None;
+        }
+    }
+
+    Some(())
+}
+
+/// Reads the characters `0` and `1` as bits, everything else is ignored.
+pub fn bits_from_text(text: &[u8]) -> Vec<bool> {
+    text.iter()
+        .filter_map(|ch| match ch {
+            b'0' => Some(false),
+            b'1' => Some(true),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn bits_from_bytes(bytes: &[u8]) -> Vec<bool> {
+    let mut out = Vec::with_capacity(bytes.len() * 8);
+    for byte in bytes {
+        for i in (0..8).rev() {
+            out.push(byte >> i & 1 == 1);
+        }
+    }
+    out
+}
+
+pub fn bit(bit: bool) -> Expr {
+    Expr::Def(Box::new(Expr::Def(Box::new(Expr::Relative(if bit {
+        0
+    } else {
+        1
+    })))))
+}
+
+/// Builds the list `a:(a head (a:(a head ...)))` that ends with `a:b:b`.
+pub fn list(items: Vec<Expr>) -> Expr {
+    let mut out = Expr::Def(Box::new(Expr::Def(Box::new(Expr::Relative(0)))));
+    for item in items.into_iter().rev() {
+        out = Expr::Def(Box::new(Expr::Group(vec![Expr::Relative(0), item, out])));
+    }
+    out
+}
+
+/// Encodes the input of a program as a list of bits or a list of bytes.
+pub fn input(bits: &[bool], mode: Mode) -> Expr {
+    match mode {
+        Mode::Bits => list(bits.iter().map(|b| bit(*b)).collect()),
+        Mode::Bytes => list(
+            bits.chunks(8)
+                .map(|byte| list(byte.iter().map(|b| bit(*b)).collect()))
+                .collect(),
+        ),
+    }
+}
+
+pub fn decode_bit(expr: &Expr) -> Option<bool> {
+    let Expr::Def(expr) = expr.unwrap() else {
+        return None;
+    };
+    let Expr::Def(expr) = expr.unwrap() else {
+        return None;
+    };
+    match expr.unwrap() {
+        Expr::Relative(1) => Some(false),
+        Expr::Relative(0) => Some(true),
+        _ => None,
+    }
+}
+
+pub fn decode_list(expr: &Expr) -> Option<Vec<&Expr>> {
+    let mut out = Vec::default();
+    let mut expr = expr.unwrap();
+
+    loop {
+        let Expr::Def(body) = expr else {
+            return None;
+        };
+
+        if let Expr::Def(end) = body.unwrap() {
+            return matches!(end.unwrap(), Expr::Relative(0)).then_some(out);
+        }
+
+        let [Expr::Relative(0), head, tail] = body.spine()[..] else {
+            return None;
+        };
+
+        out.push(head.unwrap());
+        expr = tail.unwrap();
+    }
+}
+
+/// Decodes the normal form of a program as a list of `0` and `1` or as a list of bytes.
+pub fn output(expr: &Expr, mode: Mode) -> Option<Vec<u8>> {
+    let items = decode_list(expr)?;
+    let mut out = Vec::with_capacity(items.len());
+
+    for item in items {
+        match mode {
+            Mode::Bits => out.push(if decode_bit(item)? { b'1' } else { b'0' }),
+            Mode::Bytes => {
+                let bits = decode_list(item)?;
+                if bits.len() != 8 {
+                    eprintln!("Byte has {} bits", bits.len());
+                    return None;
+                }
+
+                let mut byte = 0u8;
+                for bit in bits {
+                    byte = byte << 1 | decode_bit(bit)? as u8;
+                }
+                out.push(byte);
+            }
+        }
+    }
+
+    Some(out)
+}
+
+/// Applies `program` to `input` and decodes the normal form.
+pub fn apply(program: &Expr, input: &[bool], mode: Mode) -> Option<Vec<u8>> {
+    let expr = Expr::Group(vec![program.clone(), self::input(input, mode)]);
+    let expr = nbe::normalize(&expr);
+    let out = output(&expr, mode);
+    if out.is_none() {
+        eprintln!("The result is not a list: {}", expr.format(0));
+    }
+    out
+}
+
+/// The universal machine, reads the program from the start of `stream` and gives it the rest.
+///
+/// In [`Mode::Bytes`] the input starts at the next byte after the program.
+pub fn run(stream: &[u8], mode: Mode) -> Option<Vec<u8>> {
+    let bits = match mode {
+        Mode::Bits => bits_from_text(stream),
+        Mode::Bytes => bits_from_bytes(stream),
+    };
+
+    let (program, len) = parse(&bits)?;
+
+    let input = match mode {
+        Mode::Bits => &bits[len..],
+        Mode::Bytes => &bits[len.div_ceil(8) * 8..],
+    };
+
+    apply(&program, input, mode)
+}
+
+#[test]
+fn encode_parse() {
+    let expr = Expr::parse("a:a").unwrap();
+    assert_eq!(encode(&expr).unwrap(), bits_from_text(b"0010"));
+
+    // add 1 2
+    let expr = Expr::parse("(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))").unwrap();
+
+    let bits = encode(&expr).unwrap();
+    let (parsed, len) = parse(&bits).unwrap();
+
+    assert_eq!(len, bits.len());
+    assert_eq!(encode(&parsed).unwrap(), bits);
+}
+
+#[test]
+fn universal_machine() {
+    // a:a is cat, the program gives back the input.
+    assert_eq!(run(b"0010 1101", Mode::Bits).unwrap(), b"1101");
+    // In BLC8 a:a is padded to the byte 00100000 that is the space.
+    assert_eq!(run(b" hello", Mode::Bytes).unwrap(), b"hello");
+
+    // a:(1 0) ignores the input.
+    let program = Expr::Def(Box::new(list(vec![bit(true), bit(false)])));
+    let mut stream = encode(&program).unwrap();
+    stream.extend([false, false, true]);
+    let stream = stream
+        .iter()
+        .map(|b| if *b { b'1' } else { b'0' })
+        .collect::<Vec<u8>>();
+    assert_eq!(run(&stream, Mode::Bits).unwrap(), b"10");
+}
+
+#[test]
+fn self_interpreter() {
+    // The self-interpreter E of Tromp, `E C (M ++ N)` is `C x:M N` where M ++ N is the code of M and
+    // then the list N, M is a function of the environment it is run in.
+    let e = bits_from_text(
+        b"0100011010000000010101100000000001111000010111111001111000010111\
+          0011110000001111000010110110111001111100001111100001011110100111\
+          0100101100111000011011000010111110000111110000111001101111011111\
+          00111101110110",
+    );
+    assert_eq!(e.len(), 206);
+    let (e, len) = parse(&e).unwrap();
+    assert_eq!(len, 206);
+    // A closed program does not use the environment, so with `m:n:(m m n)` E is the universal
+    // machine
+    let universal = Expr::Group(vec![e.clone(), Expr::parse("m:n:(m m n)").unwrap()]);
+
+    let constant = encode(&Expr::Def(Box::new(list(vec![bit(true), bit(false)])))).unwrap();
+    for (program, input, out) in [
+        (bits_from_text(b"0010"), "1101", "1101"),
+        (constant, "001", "10"),
+    ] {
+        let mut stream = program;
+        stream.extend(bits_from_text(input.as_bytes()));
+        let text = stream
+            .iter()
+            .map(|b| if *b { b'1' } else { b'0' })
+            .collect::<Vec<u8>>();
+        assert_eq!(run(&text, Mode::Bits).unwrap(), out.as_bytes());
+        assert_eq!(
+            apply(&universal, &stream, Mode::Bits).unwrap(),
+            out.as_bytes()
+        );
+    }
+
+    // E gives back the programs
+    for text in [
+        "a:b:(b a (a b))",
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+    ] {
+        let program = Expr::parse(text).unwrap();
+        let expr = Expr::Group(vec![
+            e.clone(),
+            Expr::parse("m:n:m").unwrap(),
+            input(&encode(&program).unwrap(), Mode::Bits),
+        ]);
+        let program = Expr::Def(Box::new(nbe::normalize(&program)));
+        assert!(nbe::normalize(&expr).same(&program));
+    }
+}
@@ -0,0 +1,259 @@
+// Church arithmetic
+//
+// A numeral n is `a:b:(a (a ... b))` with n applications of `a`. When a known function for `add`,
+// `mul` or `exp` is applied to two numerals, the result is computed and put in place of the
+// application instead of doing the beta reductions. A numeral applied to a numeral is `exp` too,
+// `(n m)` is m to the power of n. `(0 m)` is `a:a` and not the numeral 1, so there is no shortcut
+// for the power 0.
+//
+// The functions are found by their shape, only the common definitions are known.
+
+use std::sync::OnceLock;
+
+use crate::Expr;
+
+/// Larger numerals are not built, everything on `Expr` is recursive and a numeral is as deep as
+/// its number, the REPL has the stack for this one.
+pub const LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Mul,
+    Exp,
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Mul => "mul",
+            Op::Exp => "exp",
+        }
+    }
+
+    /// The definitions that are known for the function.
+    pub fn definitions(self) -> &'static [&'static str] {
+        match self {
+            Op::Add => &[
+                "a:b:c:d:(a c (b c d))",
+                "a:b:(a c:d:e:(d(c d e)) b)",
+                "a:b:(b c:d:e:(d(c d e)) a)",
+            ],
+            Op::Mul => &["a:b:c:(a (b c))", "a:b:c:d:(a (b c) d)"],
+            Op::Exp => &["a:b:(b a)"],
+        }
+    }
+
+    pub fn apply(self, a: usize, b: usize) -> Option<usize> {
+        let result = match self {
+            Op::Add => a.checked_add(b)?,
+            Op::Mul => a.checked_mul(b)?,
+            Op::Exp if b == 0 => return None,
+            Op::Exp => a.checked_pow(u32::try_from(b).ok()?)?,
+        };
+        (result <= LIMIT).then_some(result)
+    }
+
+    fn find(expr: &Expr) -> Option<Op> {
+        // A step looks for them at every application
+        static DEFINITIONS: OnceLock<Vec<(Op, Expr)>> = OnceLock::new();
+        let definitions = DEFINITIONS.get_or_init(|| {
+            [Op::Add, Op::Mul, Op::Exp]
+                .into_iter()
+                .flat_map(|op| {
+                    op.definitions()
+                        .iter()
+                        .map(move |text| (op, Expr::parse(text).unwrap()))
+                })
+                .collect()
+        });
+        definitions
+            .iter()
+            .find(|(_, definition)| definition.same(expr))
+            .map(|(op, _)| *op)
+    }
+}
+
+/// A shortcut that was taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shortcut {
+    pub op: Op,
+    pub a: usize,
+    pub b: usize,
+    pub result: usize,
+}
+
+impl Shortcut {
+    pub fn format(&self) -> String {
+        format!("{} {} {} = {}", self.op.name(), self.a, self.b, self.result)
+    }
+}
+
+/// The number if the expression is a numeral, `a:b:b` is 0.
+pub fn numeral(expr: &Expr) -> Option<usize> {
+    let Expr::Def(body) = expr.unwrap() else {
+        return None;
+    };
+    let Expr::Def(body) = body.unwrap() else {
+        return None;
+    };
+
+    let mut num = 0;
+    let mut expr = body.unwrap();
+    loop {
+        match expr {
+            Expr::Relative(0) => return Some(num),
+            Expr::Group(exprs) if exprs.len() == 2 && exprs[0].unwrap() == &Expr::Relative(1) => {
+                num += 1;
+                expr = exprs[1].unwrap();
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Computes the leftmost outermost known function that is applied to two numerals.
+pub fn shortcut(expr: &mut Expr) -> Option<Shortcut> {
+    if numeral(expr).is_some() {
+        return None;
+    }
+
+    let Expr::Group(exprs) = expr else {
+        return match expr {
+            Expr::Def(body) => shortcut(body),
+            _ => None,
+        };
+    };
+
+    if exprs.len() == 1 {
+        return shortcut(&mut exprs[0]);
+    }
+
+    let known = |exprs: &[Expr]| match exprs {
+        [op, a, b, ..] => Some((Op::find(op)?, numeral(a)?, numeral(b)?, 3)),
+        _ => None,
+    };
+    let power = |exprs: &[Expr]| match exprs {
+        [n, m, ..] => Some((Op::Exp, numeral(m)?, numeral(n)?, 2)),
+        _ => None,
+    };
+    let found = known(exprs).or_else(|| power(exprs));
+
+    if let Some((op, a, b, len)) = found
+        && let Some(result) = op.apply(a, b)
+    {
+        exprs.splice(..len, [Expr::from(result)]);
+        if exprs.len() == 1 {
+            *expr = exprs.remove(0);
+        }
+        return Some(Shortcut { op, a, b, result });
+    }
+
+    exprs.iter_mut().find_map(shortcut)
+}
+
+#[test]
+fn numerals() {
+    for num in [0, 1, 5, 1000] {
+        assert_eq!(numeral(&Expr::from(num)), Some(num));
+    }
+    assert_eq!(numeral(&Expr::parse("a:b:(a (a b))").unwrap()), Some(2));
+    assert_eq!(numeral(&Expr::parse("a:b:(b a)").unwrap()), None);
+    assert_eq!(numeral(&Expr::parse("a:b:a").unwrap()), None);
+}
+
+#[test]
+fn shortcuts() {
+    // add 1 2 like the engine does it
+    let text = "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))";
+    let mut expr = Expr::parse(text).unwrap();
+    let shortcut = self::shortcut(&mut expr).unwrap();
+    assert_eq!(shortcut.format(), "add 1 2 = 3");
+    assert!(expr.same(&crate::nbe::normalize(&Expr::parse(text).unwrap())));
+
+    // mul (add 2 3) 4
+    let mut expr = Expr::Group(vec![
+        Expr::parse("a:b:c:(a (b c))").unwrap(),
+        Expr::Group(vec![
+            Expr::parse("a:b:c:d:(a c (b c d))").unwrap(),
+            Expr::from(2),
+            Expr::from(3),
+        ]),
+        Expr::from(4),
+    ]);
+    let mut taken = Vec::default();
+    while let Some(shortcut) = self::shortcut(&mut expr) {
+        taken.push(shortcut.op);
+    }
+    assert_eq!(taken, [Op::Add, Op::Mul]);
+    assert_eq!(numeral(&expr), Some(20));
+
+    // exp 2 10 and a numeral applied to a numeral
+    let mut expr = Expr::Group(vec![
+        Expr::parse("a:b:(b a)").unwrap(),
+        Expr::from(2),
+        Expr::from(10),
+        Expr::Label(String::from("X")),
+    ]);
+    assert_eq!(self::shortcut(&mut expr).unwrap().result, 1024);
+    assert!(matches!(&expr, Expr::Group(exprs) if exprs.len() == 2));
+
+    let mut expr = Expr::Group(vec![Expr::from(3), Expr::from(2)]);
+    assert_eq!(self::shortcut(&mut expr).unwrap().format(), "exp 2 3 = 8");
+
+    let mut expr = Expr::Group(vec![Expr::from(64), Expr::from(2)]);
+    assert_eq!(self::shortcut(&mut expr), None);
+    assert_eq!(Op::Exp.apply(2, 21), None);
+}
+
+#[test]
+fn exp_2_20() {
+    // like the REPL with `:church on`, on a stack as large as its own
+    let repl = std::thread::Builder::new().stack_size(1 << 32).spawn(|| {
+        let definitions = crate::defs::Definitions::default();
+        let expr = definitions
+            .parse("(a:b:(b a) 2 20)", crate::defs::Fix::Y)
+            .unwrap();
+        let mut trace = crate::trace::Trace::new(expr);
+        while trace.step(None, true).is_some() {}
+
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace.steps()[0].action.format(), "exp 2 20 = 1048576");
+        assert_eq!(numeral(trace.last()), Some(1 << 20));
+    });
+    repl.unwrap().join().unwrap();
+}
+
+#[test]
+fn same_as_nbe() {
+    let mut applications = Vec::default();
+    for a in 0..4 {
+        for b in 0..4 {
+            for op in [Op::Add, Op::Mul, Op::Exp] {
+                for definition in op.definitions() {
+                    applications.push(Expr::Group(vec![
+                        Expr::parse(definition).unwrap(),
+                        Expr::from(a),
+                        Expr::from(b),
+                    ]));
+                }
+            }
+            applications.push(Expr::Group(vec![Expr::from(b), Expr::from(a)]));
+        }
+    }
+
+    for application in applications {
+        let mut expr = application.clone();
+        if let Some(shortcut) = self::shortcut(&mut expr) {
+            assert!(
+                expr.same(&crate::nbe::normalize(&application)),
+                "{}",
+                shortcut.format()
+            );
+        }
+    }
+
+    let mut expr = Expr::Group(vec![Expr::from(0), Expr::from(3)]);
+    assert_eq!(self::shortcut(&mut expr), None);
+}
@@ -0,0 +1,407 @@
+// Algebraic data types
+//
+// data List = Nil | Cons head tail:List
+//
+// declares the constructors `Nil` and `Cons`, a field is recursive when it is written with the
+// type that is declared, like `tail:List`, the other fields have no type. A field cannot be named
+// like the type, it would not be clear if it is recursive. A value is a function of one case for
+// each constructor, in the order of the declaration.
+//
+// Scott     the case of the constructor is applied to the fields, `Cons h t = a:b:(b h t)`
+// Church    the recursive fields are folded first, `Cons h t = a:b:(b h (t a b))`
+//
+// case xs of Nil -> A | Cons h t -> B
+//
+// is `((xs) (A) h:t:(B))` for both, with Church `t` is the tail that is folded already. A `case`
+// goes to the end of its group, so it needs parentheses when it is not the last.
+
+use crate::infix::Token;
+use crate::{Expr, church, infix};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Encoding {
+    #[default]
+    Scott,
+    Church,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constructor {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    /// The field is of the type that is declared.
+    pub recursive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataType {
+    pub name: String,
+    pub constructors: Vec<Constructor>,
+    pub encoding: Encoding,
+}
+
+impl DataType {
+    /// `List = Nil | Cons head tail:List`
+    pub fn parse(text: &str, encoding: Encoding) -> Option<DataType> {
+        let (name, constructors) = text.split_once('=')?;
+        let name = name.trim();
+        if !is_name(name) {
+            eprintln!("`{name}` is not a name");
+            return None;
+        }
+
+        let mut parsed = Vec::<Constructor>::default();
+        for constructor in constructors.split('|') {
+            let mut words = constructor.split_whitespace();
+            let Some(constructor) = words.next() else {
+                eprintln!("A constructor is missing");
+                return None;
+            };
+            if !is_name(constructor) {
+                eprintln!("`{constructor}` is not a name");
+                return None;
+            }
+
+            let mut fields = Vec::default();
+            for word in words {
+                let (field, ty) = match word.split_once(':') {
+                    Some((field, ty)) => (field, Some(ty)),
+                    None => (word, None),
+                };
+                if !is_name(field) {
+                    eprintln!("`{field}` is not a name");
+                    return None;
+                }
+                match ty {
+                    Some(ty) if ty != name => {
+                        eprintln!("Only the fields of type {name} are written with a type");
+                        return None;
+                    }
+                    None if field == name => {
+                        eprintln!("`{field}` is the type, a recursive field is `{field}:{name}`");
+                        return None;
+                    }
+                    _ => {}
+                }
+                fields.push(Field {
+                    name: field.to_string(),
+                    recursive: ty.is_some(),
+                });
+            }
+            if parsed.iter().any(|c| c.name == constructor) {
+                eprintln!("`{constructor}` is declared twice");
+                return None;
+            }
+            parsed.push(Constructor {
+                name: constructor.to_string(),
+                fields,
+            });
+        }
+
+        Some(DataType {
+            name: name.to_string(),
+            constructors: parsed,
+            encoding,
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let constructors = self
+            .constructors
+            .iter()
+            .map(|c| {
+                let fields = c.fields.iter().map(|field| match field.recursive {
+                    true => format!("{}:{}", field.name, self.name),
+                    false => field.name.clone(),
+                });
+                [c.name.clone()].into_iter().chain(fields)
+            })
+            .map(|words| words.collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        format!("data {} = {}", self.name, constructors.join(" | "))
+    }
+
+    fn is_recursive(&self, field: &Field) -> bool {
+        self.encoding == Encoding::Church && field.recursive
+    }
+
+    /// The function of the fields that makes the value.
+    pub fn constructor(&self, i: usize) -> Expr {
+        let cases = self.constructors.len() as u32;
+        let fields = &self.constructors[i].fields;
+        let len = fields.len() as u32;
+
+        let mut exprs = vec![Expr::Relative(cases - 1 - i as u32)];
+        for (j, field) in fields.iter().enumerate() {
+            let field_id = Expr::Relative(cases + len - 1 - j as u32);
+            if self.is_recursive(field) {
+                let mut fold = vec![field_id];
+                fold.extend((0..cases).rev().map(Expr::Relative));
+                exprs.push(Expr::Group(fold));
+            } else {
+                exprs.push(field_id);
+            }
+        }
+
+        let mut expr = match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Group(exprs),
+        };
+        for _ in 0..cases + len {
+            expr = Expr::Def(Box::new(expr));
+        }
+        expr
+    }
+
+    fn decode(&self, expr: &Expr, types: &[DataType]) -> Option<String> {
+        let mut body = expr.unwrap();
+        for _ in &self.constructors {
+            let Expr::Def(inner) = body else {
+                return None;
+            };
+            body = inner.unwrap();
+        }
+        self.decode_body(body, types)
+    }
+
+    /// The body is under the functions of the cases.
+    fn decode_body(&self, body: &Expr, types: &[DataType]) -> Option<String> {
+        let cases = self.constructors.len() as u32;
+        let (head, args) = match body {
+            Expr::Group(exprs) if exprs.len() > 1 => (exprs[0].unwrap(), &exprs[1..]),
+            body => (body, &[][..]),
+        };
+        let Expr::Relative(id) = head else {
+            return None;
+        };
+        let constructor = self.constructors.get(cases.checked_sub(id + 1)? as usize)?;
+        if constructor.fields.len() != args.len() {
+            return None;
+        }
+
+        let mut out = constructor.name.clone();
+        for (field, arg) in constructor.fields.iter().zip(args) {
+            let text = if self.is_recursive(field) {
+                self.decode_body(arg.unwrap(), types)?
+            } else {
+                let arg = lower(arg, cases, 0)?;
+                decode(&arg, types)
+                    .or_else(|| church::numeral(&arg).map(|num| num.to_string()))
+                    .unwrap_or_else(|| arg.format(0))
+            };
+            match text.contains(' ') {
+                true => out.push_str(&format!(" ({text})")),
+                false => out.push_str(&format!(" {text}")),
+            }
+        }
+        Some(out)
+    }
+}
+
+/// The value in constructor syntax, like `Cons 1 (Cons 2 Nil)`, the last declared type is first.
+pub fn decode(expr: &Expr, types: &[DataType]) -> Option<String> {
+    types.iter().rev().find_map(|ty| ty.decode(expr, types))
+}
+
+/// Replaces the `case` expressions with the applications of the values to the cases.
+pub fn desugar(text: &str, types: &[DataType]) -> Option<String> {
+    let tokens = infix::tokenize(text);
+    if !tokens.contains(&word("case")) {
+        return Some(text.to_string());
+    }
+    Some(infix::format(&desugar_tokens(&tokens, types)?))
+}
+
+fn desugar_tokens(tokens: &[Token], types: &[DataType]) -> Option<Vec<Token>> {
+    let Some(start) = tokens.iter().position(|token| *token == word("case")) else {
+        return Some(tokens.to_vec());
+    };
+    let rest = &tokens[start + 1..];
+    let end = find(rest, &Token::Close).unwrap_or(rest.len());
+    let inner = &rest[..end];
+
+    let Some(of) = find(inner, &word("of")) else {
+        eprintln!("`case` needs `of`");
+        return None;
+    };
+    let scrutinee = desugar_tokens(&inner[..of], types)?;
+
+    let mut alternatives = Vec::default();
+    let mut alternative = &inner[of + 1..];
+    loop {
+        let next = find(alternative, &Token::Symbol(String::from("|")));
+        let tokens = &alternative[..next.unwrap_or(alternative.len())];
+        let Some(arrow) = find(tokens, &Token::Symbol(String::from("->"))) else {
+            eprintln!("A case needs `->`");
+            return None;
+        };
+        let mut names = Vec::default();
+        for token in &tokens[..arrow] {
+            match token {
+                Token::Word(name) if !name.ends_with(':') && !name.starts_with('\'') => {
+                    names.push(name.as_str())
+                }
+                _ => {
+                    eprintln!("A case is a constructor and the names of its fields");
+                    return None;
+                }
+            }
+        }
+        let Some((constructor, fields)) = names.split_first() else {
+            eprintln!("A case needs a constructor");
+            return None;
+        };
+        alternatives.push((*constructor, fields.to_vec(), &tokens[arrow + 1..]));
+
+        let Some(next) = next else { break };
+        alternative = &alternative[next + 1..];
+    }
+
+    let (constructor, ..) = alternatives[0];
+    let Some(ty) = types
+        .iter()
+        .rev()
+        .find(|ty| ty.constructors.iter().any(|c| c.name == constructor))
+    else {
+        eprintln!("`{constructor}` is not a constructor");
+        return None;
+    };
+
+    let mut out = tokens[..start].to_vec();
+    out.extend([Token::Open, Token::Open]);
+    out.extend(scrutinee);
+    out.push(Token::Close);
+    for declared in &ty.constructors {
+        let Some((_, fields, body)) = alternatives.iter().find(|(c, ..)| *c == declared.name)
+        else {
+            eprintln!("The case of `{}` is missing", declared.name);
+            return None;
+        };
+        if fields.len() != declared.fields.len() {
+            eprintln!("`{}` has {} fields", declared.name, declared.fields.len());
+            return None;
+        }
+        out.extend(fields.iter().map(|field| Token::Word(format!("{field}:"))));
+        out.push(Token::Open);
+        out.extend(desugar_tokens(body, types)?);
+        out.push(Token::Close);
+    }
+    if let Some((constructor, ..)) = alternatives
+        .iter()
+        .find(|(c, ..)| ty.constructors.iter().all(|d| d.name != *c))
+    {
+        eprintln!("`{constructor}` is not a constructor of {}", ty.name);
+        return None;
+    }
+    out.push(Token::Close);
+    out.extend(desugar_tokens(&rest[end..], types)?);
+    Some(out)
+}
+
+fn word(word: &str) -> Token {
+    Token::Word(word.to_string())
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(char::is_alphabetic)
+}
+
+/// The first position of the token that is not in a group, a `)` of the outer group ends the
+/// search.
+fn find(tokens: &[Token], token: &Token) -> Option<usize> {
+    let mut depth = 0;
+    for (i, next) in tokens.iter().enumerate() {
+        if depth == 0 && next == token {
+            return Some(i);
+        }
+        match next {
+            Token::Open => depth += 1,
+            Token::Close if depth == 0 => return None,
+            Token::Close => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Removes `by` variables above the depth, `None` if the expression uses them.
+fn lower(expr: &Expr, by: u32, depth: u32) -> Option<Expr> {
+    Some(match expr {
+        Expr::Group(exprs) => Expr::Group(
+            exprs
+                .iter()
+                .map(|expr| lower(expr, by, depth))
+                .collect::<Option<_>>()?,
+        ),
+        Expr::Def(body) => Expr::Def(Box::new(lower(body, by, depth + 1)?)),
+        Expr::Relative(id) if *id < depth => Expr::Relative(*id),
+        Expr::Relative(id) if *id - depth < by => return None,
+        Expr::Relative(id) => Expr::Relative(id - by),
+        Expr::Label(name) => Expr::Label(name.clone()),
+    })
+}
+
+#[test]
+fn scott() {
+    let mut definitions = crate::defs::Definitions::default();
+    definitions
+        .define("data List = Nil | Cons head tail:List")
+        .unwrap();
+    definitions.define("data Pair = Pair first second").unwrap();
+
+    let expr = definitions
+        .parse(
+            "(Cons a:b:(a b) (Cons a:b:(a (a b)) Nil))",
+            crate::defs::Fix::Y,
+        )
+        .unwrap();
+    let normal = crate::nbe::normalize(&expr);
+    assert_eq!(definitions.decode(&normal).unwrap(), "Cons 1 (Cons 2 Nil)");
+
+    let text =
+        "(case (Pair 'A (Cons 'B Nil)) of Pair a b -> (case b of Cons h t -> h | Nil -> 'X))";
+    let expr = definitions.parse(text, crate::defs::Fix::Y).unwrap();
+    assert_eq!(crate::nbe::normalize(&expr), Expr::Label(String::from("B")));
+
+    let types = [DataType::parse("List = Nil | Cons head tail:List", Encoding::Scott).unwrap()];
+    assert_eq!(types[0].format(), "data List = Nil | Cons head tail:List");
+    assert_eq!(
+        desugar("x:(case x of Cons h t -> t | Nil -> '|)", &types).unwrap(),
+        "x:(((x) ('|) h:t:(t)))"
+    );
+    assert!(desugar("case x of Cons h -> h | Nil -> x", &types).is_none());
+    assert!(desugar("case x of Cons h t -> h", &types).is_none());
+    assert_eq!(desugar("x:('case x)", &types).unwrap(), "x:('case x)");
+
+    // a field named like the type is not clear, only the type itself is a type of a field
+    assert!(DataType::parse("List = Nil | Cons head List", Encoding::Scott).is_none());
+    assert!(DataType::parse("List = Nil | Cons head:Pair tail:List", Encoding::Scott).is_none());
+}
+
+#[test]
+fn church() {
+    let mut definitions = crate::defs::Definitions::default();
+    definitions.encoding = Encoding::Church;
+    definitions
+        .define("data List = Nil | Cons head tail:List")
+        .unwrap();
+    definitions.define("add = a:b:c:d:(a c (b c d))").unwrap();
+
+    let list = "(Cons a:b:(a b) (Cons a:b:(a (a b)) (Cons a:b:(a (a (a b))) Nil)))";
+    let expr = definitions.parse(list, crate::defs::Fix::Y).unwrap();
+    let normal = crate::nbe::normalize(&expr);
+    assert_eq!(
+        definitions.decode(&normal).unwrap(),
+        "Cons 1 (Cons 2 (Cons 3 Nil))"
+    );
+
+    // the tail is the sum of the tail already
+    let text = format!("case {list} of Nil -> a:b:b | Cons h t -> add h t");
+    let expr = definitions.parse(&text, crate::defs::Fix::Y).unwrap();
+    assert!(crate::nbe::normalize(&expr).same(&Expr::from(6)));
+}
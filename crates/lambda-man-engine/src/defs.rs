@@ -0,0 +1,481 @@
+// Definitions
+//
+// name = EXPR                  EXPR can use the names that are defined before
+// rec name = EXPR              EXPR can use name too
+// letrec a = EXPR; b = EXPR    every EXPR can use every name of the group
+// data L = Nil | Cons h t:L    defines the constructors, see `data`
+// infixl 6 + add              `a + b` is `(add a b)`, see `infix`
+//
+// The names of other modules are added with `import`, see `modules`.
+//
+// The names are replaced by their definitions when an expression is parsed. A recursive definition
+// is `FIX name:EXPR` where FIX is Y, or Z when the arguments are evaluated first, because `Y f`
+// does not stop then. A group is one fixed point of a tuple `s:(s A B)` where `a` in A and B is
+// `(t a:b:a)` and `b` is `(t a:b:b)`, every name is its element of the tuple.
+//
+// The definitions are kept as they are written, so the fixed point is chosen when they are used.
+
+use std::rc::Rc;
+
+use crate::reduce::Strategy;
+use crate::{Expr, data, infix};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fix {
+    /// `f:(x:(f (x x)) x:(f (x x)))`
+    Y,
+    /// `f:(x:(f v:(x x v)) x:(f v:(x x v)))`
+    Z,
+}
+
+impl Fix {
+    pub fn name(self) -> &'static str {
+        match self {
+            Fix::Y => "Y",
+            Fix::Z => "Z",
+        }
+    }
+
+    pub fn to_expr(self) -> Expr {
+        let text = match self {
+            Fix::Y => "f:(x:(f (x x)) x:(f (x x)))",
+            Fix::Z => "f:(x:(f v:(x x v)) x:(f v:(x x v)))",
+        };
+        Expr::parse(text).unwrap()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Body {
+    Plain(Expr),
+    /// Uses its own name as the variable 0.
+    Rec(Expr),
+    /// The group and the index in it, the bodies use the names of the group as the variables.
+    Group(Rc<Vec<Expr>>, usize),
+    /// The definition at the index in the definitions of a module.
+    Import(Rc<Definitions>, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Definition {
+    name: String,
+    body: Body,
+}
+
+#[derive(Debug, Default)]
+pub struct Definitions {
+    definitions: Vec<Definition>,
+    types: Vec<data::DataType>,
+    operators: Vec<infix::Operator>,
+    /// Of the types that are declared next.
+    pub encoding: data::Encoding,
+}
+
+/// The definitions that the REPL starts with.
+pub const PRELUDE: &str = include_str!("prelude.lam");
+
+impl Definitions {
+    pub fn prelude() -> Definitions {
+        let mut definitions = Definitions::default();
+        for line in PRELUDE.lines() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with("//") {
+                definitions.define(line).expect("The prelude is defined");
+            }
+        }
+        definitions
+    }
+
+    /// Is the line a definition and not an expression.
+    pub fn is_statement(line: &str) -> bool {
+        let line = line.trim_start();
+        if ["rec ", "letrec ", "data ", "infixl ", "infixr ", "infix "]
+            .iter()
+            .any(|keyword| line.starts_with(keyword))
+        {
+            return true;
+        }
+        // `a == b` is an operator
+        line.split_once('=')
+            .is_some_and(|(name, rest)| is_name(name.trim()) && !rest.starts_with(infix::is_symbol))
+    }
+
+    /// Adds the definitions of the line, returns the names.
+    pub fn define(&mut self, line: &str) -> Option<Vec<String>> {
+        let line = line.trim();
+
+        if line.starts_with("infix") {
+            let operator = infix::Operator::parse(line)?;
+            let symbol = operator.symbol.clone();
+            self.operators.push(operator);
+            return Some(vec![symbol]);
+        }
+
+        if let Some(declaration) = line.strip_prefix("data ") {
+            let ty = data::DataType::parse(declaration, self.encoding)?;
+            let mut names = Vec::default();
+            for (i, constructor) in ty.constructors.iter().enumerate() {
+                self.definitions.push(Definition {
+                    name: constructor.name.clone(),
+                    body: Body::Plain(ty.constructor(i)),
+                });
+                names.push(constructor.name.clone());
+            }
+            self.types.push(ty);
+            return Some(names);
+        }
+
+        if let Some(group) = line.strip_prefix("letrec ") {
+            let mut names = Vec::default();
+            let mut texts = Vec::default();
+            for binding in group.split(';') {
+                let (name, text) = split(binding)?;
+                names.push(name);
+                texts.push(text);
+            }
+
+            let bodies = texts
+                .iter()
+                .map(|text| self.parse_with(text, &names))
+                .collect::<Option<Vec<_>>>()?;
+            let bodies = Rc::new(bodies);
+            for (i, name) in names.iter().enumerate() {
+                self.definitions.push(Definition {
+                    name: name.clone(),
+                    body: Body::Group(bodies.clone(), i),
+                });
+            }
+            return Some(names);
+        }
+
+        let (body, name) = match line.strip_prefix("rec ") {
+            Some(line) => {
+                let (name, text) = split(line)?;
+                (
+                    Body::Rec(self.parse_with(text, std::slice::from_ref(&name))?),
+                    name,
+                )
+            }
+            None => {
+                let (name, text) = split(line)?;
+                (Body::Plain(self.parse_with(text, &[])?), name)
+            }
+        };
+        self.definitions.push(Definition {
+            name: name.clone(),
+            body,
+        });
+        Some(vec![name])
+    }
+
+    /// Parses an expression that can use the names.
+    pub fn parse(&self, text: &str, fix: Fix) -> Option<Expr> {
+        let expr = self.parse_with(text, &[])?;
+        Some(self.resolve(expr, self.definitions.len(), 0, fix, &mut Vec::default()))
+    }
+
+    /// The expression of the last definition of the name.
+    pub fn value(&self, name: &str, fix: Fix) -> Option<Expr> {
+        let i = self.definitions.iter().rposition(|d| d.name == name)?;
+        Some(self.value_at(i, fix, &mut Vec::default()))
+    }
+
+    /// The value in the constructor syntax of the declared types.
+    pub fn decode(&self, expr: &Expr) -> Option<String> {
+        data::decode(expr, &self.types)
+    }
+
+    /// Replaces the recursive definitions in the expression with labels of their names.
+    pub fn fold(&self, expr: &Expr, fix: Fix) -> Expr {
+        let mut values = Vec::default();
+        let folded = (0..self.definitions.len())
+            .filter(|i| self.is_recursive(*i))
+            .map(|i| {
+                let value = self.value_at(i, fix, &mut values);
+                (self.definitions[i].name.clone(), value)
+            })
+            .collect::<Vec<_>>();
+
+        if folded.is_empty() {
+            return expr.clone();
+        }
+        fold(expr, &folded)
+    }
+
+    /// The strategy for the expression, the innermost redex of a recursive definition is in the
+    /// fixed point again after it is reduced, so an expression that uses one is reduced in normal
+    /// order.
+    pub fn strategy(&self, expr: &Expr, fix: Fix, strategy: Strategy) -> Strategy {
+        match strategy {
+            Strategy::Innermost if self.fold(expr, fix) != *expr => Strategy::Normal,
+            strategy => strategy,
+        }
+    }
+
+    /// The names that are defined here and not imported, the last definition of each.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::<String>::default();
+        for definition in self.definitions.iter().rev() {
+            if !matches!(definition.body, Body::Import(..)) && !names.contains(&definition.name) {
+                names.push(definition.name.clone());
+            }
+        }
+        names.reverse();
+        names
+    }
+
+    /// Adds the exported names of a module as `qualifier.name`, and as `name` too when they are
+    /// not only qualified. The operators and the types of the exported names come with them.
+    pub fn import(
+        &mut self,
+        module: &Rc<Definitions>,
+        exports: &[String],
+        qualifier: &str,
+        unqualified: bool,
+    ) -> Vec<String> {
+        let mut prefixes = vec![format!("{qualifier}.")];
+        if unqualified {
+            prefixes.push(String::default());
+        }
+
+        let mut names = Vec::default();
+        for export in exports {
+            let Some(i) = module.definitions.iter().rposition(|d| d.name == *export) else {
+                continue;
+            };
+            for prefix in &prefixes {
+                let name = format!("{prefix}{export}");
+                self.definitions.push(Definition {
+                    name: name.clone(),
+                    body: Body::Import(module.clone(), i),
+                });
+                names.push(name);
+            }
+        }
+
+        for ty in &module.types {
+            if !ty.constructors.iter().all(|c| exports.contains(&c.name)) {
+                continue;
+            }
+            for prefix in &prefixes {
+                let mut ty = ty.clone();
+                for constructor in ty.constructors.iter_mut() {
+                    constructor.name.insert_str(0, prefix);
+                }
+                self.types.push(ty);
+            }
+        }
+
+        for operator in &module.operators {
+            if exports.contains(&operator.definition) {
+                let mut operator = operator.clone();
+                // the name without the qualifier when there is one
+                operator.definition.insert_str(0, prefixes.last().unwrap());
+                self.operators.push(operator);
+            }
+        }
+
+        names
+    }
+
+    fn is_recursive(&self, i: usize) -> bool {
+        match &self.definitions[i].body {
+            Body::Plain(_) => false,
+            Body::Rec(_) | Body::Group(..) => true,
+            Body::Import(module, i) => module.is_recursive(*i),
+        }
+    }
+
+    fn parse_with(&self, text: &str, extra: &[String]) -> Option<Expr> {
+        let names = self.definitions.iter().map(|d| &d.name).chain(extra);
+        let count = self.definitions.len() + extra.len();
+        let scope = names
+            .enumerate()
+            .map(|(i, name)| (name.clone(), (count - 1 - i) as u32))
+            .collect();
+
+        let text = data::desugar(text, &self.types)?;
+        let text = infix::desugar(&text, &self.operators)?;
+        let mut error = false;
+        let expr = Expr::_parse(&text, &mut 0, &mut error, scope)?;
+        (!error).then_some(expr)
+    }
+
+    /// Replaces the first `count` definitions that are above the `extra` variables.
+    fn resolve(
+        &self,
+        mut expr: Expr,
+        count: usize,
+        extra: u32,
+        fix: Fix,
+        values: &mut Vec<Option<Expr>>,
+    ) -> Expr {
+        for i in (0..count).rev() {
+            let value = self.value_at(i, fix, values);
+            expr.replace_relative(extra, value);
+        }
+        expr
+    }
+
+    fn value_at(&self, i: usize, fix: Fix, values: &mut Vec<Option<Expr>>) -> Expr {
+        if values.len() < self.definitions.len() {
+            values.resize(self.definitions.len(), None);
+        }
+        if let Some(value) = &values[i] {
+            return value.clone();
+        }
+
+        let value = match &self.definitions[i].body {
+            Body::Plain(body) => self.resolve(body.clone(), i, 0, fix, values),
+            Body::Import(module, i) => module.value_at(*i, fix, &mut Vec::default()),
+            Body::Rec(body) => {
+                let body = self.resolve(body.clone(), i, 1, fix, values);
+                Expr::Group(vec![fix.to_expr(), Expr::Def(Box::new(body))])
+            }
+            Body::Group(bodies, index) => {
+                let start = i - index;
+                let len = bodies.len() as u32;
+
+                let mut tuple = vec![Expr::Relative(0)];
+                for body in bodies.iter() {
+                    let mut body = self.resolve(body.clone(), start, len, fix, values);
+                    // the tuple `t` is above the names of the group
+                    for j in (1..=len).rev() {
+                        body.replace_relative(
+                            0,
+                            Expr::Group(vec![Expr::Relative(j - 1), select(j, len)]),
+                        );
+                    }
+                    body.update(0, 1);
+                    tuple.push(body);
+                }
+
+                let tuple = Expr::Def(Box::new(Expr::Def(Box::new(Expr::Group(tuple)))));
+                let fixed = Expr::Group(vec![fix.to_expr(), tuple]);
+                Expr::Group(vec![fixed, select(*index as u32 + 1, len)])
+            }
+        };
+
+        values[i] = Some(value.clone());
+        value
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(char::is_alphabetic)
+}
+
+/// `name = EXPR`
+fn split(binding: &str) -> Option<(String, &str)> {
+    let (name, text) = binding.split_once('=')?;
+    let name = name.trim();
+    if !is_name(name) {
+        eprintln!("`{name}` is not a name");
+        return None;
+    }
+    Some((name.to_string(), text.trim()))
+}
+
+/// The function that returns its `i`-th argument of `len`, from 1.
+fn select(i: u32, len: u32) -> Expr {
+    let mut expr = Expr::Relative(len - i);
+    for _ in 0..len {
+        expr = Expr::Def(Box::new(expr));
+    }
+    expr
+}
+
+fn fold(expr: &Expr, folded: &[(String, Expr)]) -> Expr {
+    if expr.is_contained(0)
+        && let Some((name, _)) = folded.iter().find(|(_, value)| value.same(expr))
+    {
+        return Expr::Label(name.clone());
+    }
+
+    match expr {
+        Expr::Group(exprs) => Expr::Group(exprs.iter().map(|expr| fold(expr, folded)).collect()),
+        Expr::Def(body) => Expr::Def(Box::new(fold(body, folded))),
+        expr => expr.clone(),
+    }
+}
+
+#[cfg(test)]
+fn church() -> Definitions {
+    let mut definitions = Definitions::default();
+    for line in [
+        "true = a:b:a",
+        "false = a:b:b",
+        "iszero = n:(n (x:false) true)",
+        "pred = n:f:x:(n (g:h:(h (g f))) (u:x) (u:u))",
+    ] {
+        assert!(Definitions::is_statement(line));
+        definitions.define(line).unwrap();
+    }
+    definitions
+}
+
+#[test]
+fn recursion() {
+    let mut definitions = church();
+    assert!(!Definitions::is_statement("(a:a 'X=Y)"));
+
+    definitions
+        .define("rec sum = n:(iszero n n f:x:(n f (sum (pred n) f x)))")
+        .unwrap();
+    let expr = definitions
+        .parse("(sum a:b:(a (a (a b))))", Fix::Y)
+        .unwrap();
+    // 3 + 2 + 1
+    assert!(crate::nbe::normalize(&expr).same(&Expr::from(6)));
+    // the default strategy of the REPL would unfold `Y f` forever
+    let strategy = definitions.strategy(&expr, Fix::Y, Strategy::default());
+    assert_eq!(strategy, Strategy::Normal);
+    assert!(crate::reduce::normalize(&expr, strategy).same(&Expr::from(6)));
+    let plain = definitions.parse("(pred a:b:(a b))", Fix::Y).unwrap();
+    assert_eq!(
+        definitions.strategy(&plain, Fix::Y, Strategy::default()),
+        Strategy::Innermost
+    );
+
+    let folded = definitions.fold(&expr, Fix::Y);
+    assert_eq!(folded.format(0), "('sum a:b:(a (a (a b))))");
+    // the redexes in the value of sum are shown on its name
+    let candidates = expr.find_beta_reductions();
+    assert!(!candidates.is_empty());
+    for (_, at) in candidates {
+        assert_eq!(folded.valid_path(&at), [0]);
+    }
+    assert_eq!(definitions.value("sum", Fix::Z).unwrap().format(0).len(), {
+        definitions.value("sum", Fix::Y).unwrap().format(0).len() + 8
+    });
+}
+
+#[test]
+fn mutual_recursion() {
+    let mut definitions = church();
+    let names = definitions
+        .define(
+            "letrec even = n:(iszero n (d:true) (d:(odd (pred n))) iszero); \
+             odd = n:(iszero n (d:false) (d:(even (pred n))) iszero)",
+        )
+        .unwrap();
+    assert_eq!(names, ["even", "odd"]);
+
+    for (fix, kind) in [(Fix::Y, None), (Fix::Z, Some(crate::machine::Kind::Cek))] {
+        for (num, even) in [(0, true), (3, false), (4, true)] {
+            let mut expr = definitions.parse("even", fix).unwrap();
+            expr = Expr::Group(vec![expr, Expr::from(num)]);
+
+            let result = match kind {
+                None => crate::nbe::normalize(&expr),
+                Some(kind) => {
+                    let mut machine = crate::machine::Machine::new(kind, &expr);
+                    while machine.step() {}
+                    machine.result()
+                }
+            };
+            let expected = definitions.value(if even { "true" } else { "false" }, fix);
+            assert!(result.same(&expected.unwrap()));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Call-by-need graph reduction
+//
+// The expression is a graph of nodes, an argument is not copied into the function, every
+// occurrence points to the same node. When an application is reduced the node is overwritten with
+// the result, so the other occurrences do not reduce it again.
+//
+// Only the parts of a function body that use the variable are copied when the function is called,
+// the rest of the body is shared.
+
+use crate::Expr;
+use crate::reduce::Strategy;
+
+#[derive(Debug, Clone)]
+enum Node {
+    App(usize, usize),
+    Lam(usize),
+    Var(u32),
+    /// The variable of a function that is being normalized, at this depth.
+    Free(u32),
+    Label(String),
+    /// `()`
+    Empty,
+    /// The node was reduced to this one.
+    Ind(usize),
+}
+
+#[derive(Default)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    /// How many beta reductions were made.
+    pub betas: usize,
+}
+
+impl Graph {
+    fn add(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    pub fn insert(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Group(exprs) => {
+                let Some((first, rest)) = exprs.split_first() else {
+                    return self.add(Node::Empty);
+                };
+                let mut node = self.insert(first);
+                for expr in rest {
+                    let arg = self.insert(expr);
+                    node = self.add(Node::App(node, arg));
+                }
+                node
+            }
+            Expr::Def(body) => {
+                let body = self.insert(body);
+                self.add(Node::Lam(body))
+            }
+            Expr::Relative(id) => self.add(Node::Var(*id)),
+            Expr::Label(name) => self.add(Node::Label(name.clone())),
+        }
+    }
+
+    fn follow(&self, mut node: usize) -> usize {
+        while let Node::Ind(next) = self.nodes[node] {
+            node = next;
+        }
+        node
+    }
+
+    /// Copies `node` with the variable at `depth` replaced by `arg`, returns `None` when it does not
+    /// use the variable so it can be shared.
+    fn instantiate(&mut self, node: usize, depth: u32, arg: usize) -> Option<usize> {
+        let node = self.follow(node);
+        match self.nodes[node] {
+            Node::App(function, argument) => {
+                let new_function = self.instantiate(function, depth, arg);
+                let new_argument = self.instantiate(argument, depth, arg);
+                if new_function.is_none() && new_argument.is_none() {
+                    return None;
+                }
+                Some(self.add(Node::App(
+                    new_function.unwrap_or(function),
+                    new_argument.unwrap_or(argument),
+                )))
+            }
+            Node::Lam(body) => {
+                let body = self.instantiate(body, depth + 1, arg)?;
+                Some(self.add(Node::Lam(body)))
+            }
+            Node::Var(id) if id == depth => Some(arg),
+            Node::Var(id) if id > depth => Some(self.add(Node::Var(id - 1))),
+            _ => None,
+        }
+    }
+
+    /// Reduces the node until it is not an application of a function.
+    pub fn whnf(&mut self, node: usize) -> usize {
+        let node = self.follow(node);
+        let Node::App(function, arg) = self.nodes[node] else {
+            return node;
+        };
+
+        let function = self.whnf(function);
+        let Node::Lam(body) = self.nodes[function] else {
+            self.nodes[node] = Node::App(function, arg);
+            return node;
+        };
+
+        self.betas += 1;
+        let result = self.instantiate(body, 0, arg).unwrap_or(body);
+        let result = self.whnf(result);
+        self.nodes[node] = Node::Ind(result);
+        result
+    }
+
+    /// The beta normal form of the node, does not return if it has none.
+    pub fn normalize(&mut self, node: usize, depth: u32) -> Expr {
+        let node = self.whnf(node);
+        match self.nodes[node].clone() {
+            Node::App(function, arg) => {
+                let mut exprs = match self.normalize(function, depth) {
+                    Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                    function => vec![function],
+                };
+                exprs.push(self.normalize(arg, depth));
+                Expr::Group(exprs)
+            }
+            Node::Lam(body) => {
+                let var = self.add(Node::Free(depth));
+                let body = self.instantiate(body, 0, var).unwrap_or(body);
+                Expr::Def(Box::new(self.normalize(body, depth + 1)))
+            }
+            Node::Free(level) => Expr::Relative(depth - level - 1),
+            Node::Var(id) => Expr::Relative(id),
+            Node::Label(name) => Expr::Label(name),
+            Node::Empty => Expr::Group(Vec::default()),
+            Node::Ind(_) => unreachable!(),
+        }
+    }
+}
+
+/// How many beta reductions were made with and without sharing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sharing {
+    pub shared: usize,
+    pub tree: usize,
+}
+
+impl Sharing {
+    pub fn saved(&self) -> isize {
+        self.tree as isize - self.shared as isize
+    }
+}
+
+/// The beta normal form with call-by-need, does not return if the expression has none.
+pub fn normalize(expr: &Expr) -> (Expr, usize) {
+    let mut graph = Graph::default();
+    let node = graph.insert(expr);
+    let expr = graph.normalize(node, 0);
+    (expr, graph.betas)
+}
+
+/// Normalizes the expression with sharing and with the engine.
+pub fn compare(expr: &Expr) -> (Expr, Sharing) {
+    let (normal, shared) = normalize(expr);
+
+    let tree = expr.reductions(Strategy::Innermost).count();
+    (normal, Sharing { shared, tree })
+}
+
+#[test]
+fn sharing() {
+    // `(h h)` is used three times but reduced once
+    let expr = Expr::parse("((h:(x:(x x x) (h h))) (a:a))").unwrap();
+    let (normal, sharing) = compare(&expr);
+    assert_eq!(normal, Expr::parse("a:a").unwrap());
+    assert_eq!(sharing, Sharing { shared: 5, tree: 7 });
+
+    for text in [
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        // sub 10 6
+        "((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f(f(f(f(f(f(f(f x))))))))))) (f:x:(f(f(f(f(f(f x))))))))",
+        "((a:b:b) ((a:(a a)) (a:(a a))))",
+        "(x:((y:y) x 'X))",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        assert!(normalize(&expr).0.same(&crate::nbe::normalize(&expr)));
+    }
+}
@@ -0,0 +1,401 @@
+// Infix operators
+//
+// infixl 6 + add
+//
+// declares `a + b` as `(add a b)`, the operators with the higher precedence are applied first and
+// an application is before all of them, `f a + b` is `(add (f a) b)`. `infixr` groups to the right
+// and the operators of `infix` cannot follow each other without parentheses. `(+)` alone is `add`.
+//
+// The numbers are Church numerals, so `1 + 2 + 2` is `(add (add 1 2) 2)`. A function `x:` is
+// applied to the next term only, like everywhere else, so `x:(x + 1)` needs the parentheses. A
+// number is at most `LIMIT`, a larger one is made with the operators, like `2 ^ 20`.
+
+use crate::Expr;
+
+/// The largest number that is written. Its numeral is written out and parsed again and a redex is
+/// looked for in every part of it, so a deeper one overflows the stack or the memory.
+pub const LIMIT: usize = 1 << 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fixity {
+    Left,
+    Right,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operator {
+    pub symbol: String,
+    pub precedence: u8,
+    pub fixity: Fixity,
+    /// The name of the definition that is applied.
+    pub definition: String,
+}
+
+impl Operator {
+    /// `infixl 6 + add`
+    pub fn parse(line: &str) -> Option<Operator> {
+        let mut words = line.split_whitespace();
+        let fixity = match words.next()? {
+            "infixl" => Fixity::Left,
+            "infixr" => Fixity::Right,
+            "infix" => Fixity::None,
+            _ => return None,
+        };
+        let (Some(precedence), Some(symbol), Some(definition), None) =
+            (words.next(), words.next(), words.next(), words.next())
+        else {
+            eprintln!("Expected `infixl PRECEDENCE SYMBOL NAME`");
+            return None;
+        };
+
+        let Ok(precedence) = precedence.parse() else {
+            eprintln!("`{precedence}` is not a precedence");
+            return None;
+        };
+        if !symbol.chars().all(is_symbol) {
+            eprintln!("`{symbol}` is not an operator");
+            return None;
+        }
+        if !definition.chars().all(|ch| ch.is_alphabetic() || ch == '.') {
+            eprintln!("`{definition}` is not a name");
+            return None;
+        }
+
+        Some(Operator {
+            symbol: symbol.to_string(),
+            precedence,
+            fixity,
+            definition: definition.to_string(),
+        })
+    }
+
+    pub fn format(&self) -> String {
+        let fixity = match self.fixity {
+            Fixity::Left => "infixl",
+            Fixity::Right => "infixr",
+            Fixity::None => "infix",
+        };
+        format!(
+            "{fixity} {} {} {}",
+            self.precedence, self.symbol, self.definition
+        )
+    }
+}
+
+pub fn is_symbol(ch: char) -> bool {
+    !(ch.is_alphanumeric() || ch.is_whitespace() || "()':\\λ".contains(ch))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Open,
+    Close,
+    /// A name, a label or `x:`, as it is written.
+    Word(String),
+    Number(usize),
+    Symbol(String),
+}
+
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::default();
+    let mut i = 0;
+
+    let take = |i: &mut usize, f: &dyn Fn(char) -> bool| {
+        let start = *i;
+        while *i < chars.len() && f(chars[*i]) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>()
+    };
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+        } else if ch == '(' {
+            tokens.push(Token::Open);
+            i += 1;
+        } else if ch == ')' {
+            tokens.push(Token::Close);
+            i += 1;
+        } else if ch == '\'' {
+            i += 1;
+            let label = take(&mut i, &|ch| {
+                !(ch.is_whitespace() || ch == '(' || ch == ')')
+            });
+            tokens.push(Token::Word(format!("'{label}")));
+        } else if ch.is_alphabetic() {
+            let mut name = take(&mut i, &|ch| ch.is_alphanumeric() || ch == '.');
+            if chars.get(i) == Some(&':') {
+                name.push(':');
+                i += 1;
+            }
+            tokens.push(Token::Word(name));
+        } else if ch.is_ascii_digit() {
+            let digits = take(&mut i, &|ch| ch.is_ascii_digit());
+            tokens.push(Token::Number(digits.parse().unwrap_or(usize::MAX)));
+        } else {
+            tokens.push(Token::Symbol(take(&mut i, &is_symbol)));
+            // a character that is not a symbol and not known to the tokens, like `\`
+            if matches!(tokens.last(), Some(Token::Symbol(symbol)) if symbol.is_empty()) {
+                tokens.pop();
+                tokens.push(Token::Word(ch.to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// The text of the tokens, it is tokenized to the same tokens.
+pub fn format(tokens: &[Token]) -> String {
+    let mut out = String::default();
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        let space = match (previous, token) {
+            (None | Some(Token::Open), _) | (_, Token::Close) => false,
+            (Some(Token::Word(word)), _) => !word.ends_with(':'),
+            _ => true,
+        };
+        if space {
+            out.push(' ');
+        }
+        match token {
+            Token::Open => out.push('('),
+            Token::Close => out.push(')'),
+            Token::Word(word) | Token::Symbol(word) => out.push_str(word),
+            Token::Number(num) => out.push_str(&num.to_string()),
+        }
+        previous = Some(token);
+    }
+    out
+}
+
+/// The applications and the operators between them.
+enum Item {
+    Operand(Vec<String>),
+    Operator(Operator),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    i: usize,
+    operators: &'a [Operator],
+}
+
+impl Parser<'_> {
+    /// The terms until the end of the group, without the parentheses.
+    fn group(&mut self) -> Option<String> {
+        let mut items = Vec::<Item>::default();
+
+        while let Some(token) = self.tokens.get(self.i).cloned() {
+            if token == Token::Close {
+                break;
+            }
+            self.i += 1;
+
+            if let Token::Symbol(symbol) = &token {
+                let Some(operator) = self.operators.iter().rev().find(|o| o.symbol == *symbol)
+                else {
+                    eprintln!("Unknown operator `{symbol}`");
+                    return None;
+                };
+                items.push(Item::Operator(operator.clone()));
+                continue;
+            }
+
+            let term = self.term(token)?;
+            match items.last_mut() {
+                Some(Item::Operand(terms)) => terms.push(term),
+                _ => items.push(Item::Operand(vec![term])),
+            }
+        }
+
+        if let [Item::Operator(operator)] = items.as_slice() {
+            return Some(operator.definition.clone());
+        }
+        let mut i = 0;
+        let out = self.operators(&items, &mut i, 0)?;
+        if i != items.len() {
+            eprintln!("An operator needs a term on both sides");
+            return None;
+        }
+        Some(out)
+    }
+
+    /// A name, a number, a group or a function of those.
+    fn term(&mut self, token: Token) -> Option<String> {
+        Some(match token {
+            Token::Open => {
+                let group = self.group()?;
+                if self.tokens.get(self.i) == Some(&Token::Close) {
+                    self.i += 1;
+                }
+                format!("({group})")
+            }
+            Token::Word(word) if word.ends_with(':') => {
+                let Some(token) = self
+                    .tokens
+                    .get(self.i)
+                    .filter(|token| !matches!(token, Token::Close | Token::Symbol(_)))
+                    .cloned()
+                else {
+                    eprintln!("`{word}` needs a body");
+                    return None;
+                };
+                self.i += 1;
+                format!("{word}{}", self.term(token)?)
+            }
+            Token::Word(word) => word,
+            Token::Number(num) if num > LIMIT => {
+                eprintln!("{num} is too large, the numbers go up to {LIMIT}");
+                return None;
+            }
+            Token::Number(num) => Expr::from(num).format(0),
+            Token::Close | Token::Symbol(_) => unreachable!(),
+        })
+    }
+
+    fn operators(&self, items: &[Item], i: &mut usize, min: u8) -> Option<String> {
+        let Some(Item::Operand(terms)) = items.get(*i) else {
+            eprintln!("An operator needs a term on both sides");
+            return None;
+        };
+        *i += 1;
+        let mut left = match terms.as_slice() {
+            [term] => term.clone(),
+            terms => format!("({})", terms.join(" ")),
+        };
+
+        let mut last = None::<&Operator>;
+        while let Some(Item::Operator(operator)) = items.get(*i) {
+            if operator.precedence < min {
+                break;
+            }
+            if let Some(last) = last
+                && last.precedence == operator.precedence
+                && (last.fixity == Fixity::None || operator.fixity == Fixity::None)
+            {
+                eprintln!(
+                    "`{}` and `{}` need parentheses",
+                    last.symbol, operator.symbol
+                );
+                return None;
+            }
+            *i += 1;
+
+            let next = match operator.fixity {
+                Fixity::Right => operator.precedence,
+                Fixity::Left | Fixity::None => operator.precedence.checked_add(1)?,
+            };
+            let right = self.operators(items, i, next)?;
+            left = format!("({} {left} {right})", operator.definition);
+            last = Some(operator);
+        }
+
+        Some(left)
+    }
+}
+
+/// Replaces the operators with the applications of their definitions and the numbers with
+/// numerals, a text without them stays as it is.
+pub fn desugar(text: &str, operators: &[Operator]) -> Option<String> {
+    let tokens = tokenize(text);
+    if !tokens
+        .iter()
+        .any(|token| matches!(token, Token::Number(_) | Token::Symbol(_)))
+    {
+        return Some(text.to_string());
+    }
+
+    let mut parser = Parser {
+        tokens,
+        i: 0,
+        operators,
+    };
+    let mut out = parser.group()?;
+    // the closing parentheses that have no opening one
+    while parser.i < parser.tokens.len() {
+        parser.i += 1;
+        out.push(')');
+        out.push_str(&parser.group()?);
+    }
+    Some(out)
+}
+
+#[test]
+fn precedence() {
+    let operators = [
+        "infixl 6 + add",
+        "infixl 7 * mul",
+        "infixr 8 ^ exp",
+        "infix 4 == eq",
+    ]
+    .map(|line| Operator::parse(line).unwrap());
+    assert_eq!(operators[2].format(), "infixr 8 ^ exp");
+
+    let desugar = |text| self::desugar(text, &operators);
+    assert_eq!(desugar("a + b * c").unwrap(), "(add a (mul b c))");
+    assert_eq!(desugar("f a + b + c").unwrap(), "(add (add (f a) b) c)");
+    assert_eq!(desugar("a ^ b ^ c").unwrap(), "(exp a (exp b c))");
+    assert_eq!(desugar("x:(x * y) (+)").unwrap(), "(x:((mul x y)) (add))");
+    assert_eq!(desugar("(a:b:(a b) c)").unwrap(), "(a:b:(a b) c)");
+    assert_eq!(desugar("1").unwrap(), "(a:b:(a b))");
+    assert!(self::desugar(&LIMIT.to_string(), &operators).is_some());
+    assert!(self::desugar(&(LIMIT + 1).to_string(), &operators).is_none());
+    assert!(desugar("16777216").is_none());
+    assert!(desugar("a == b == c").is_none());
+    assert!(desugar("a + ").is_none());
+    assert!(desugar("a - b").is_none());
+}
+
+#[test]
+fn numerals() {
+    let mut definitions = crate::defs::Definitions::default();
+    for line in [
+        "add = a:b:c:d:(a c (b c d))",
+        "mul = a:b:c:(a (b c))",
+        "exp = a:b:(b a)",
+        "infixl 6 + add",
+        "infixl 7 * mul",
+        "infixr 8 ^ exp",
+    ] {
+        assert!(crate::defs::Definitions::is_statement(line));
+        definitions.define(line).unwrap();
+    }
+
+    for (text, num) in [
+        ("1 + 2 + 2", 5),
+        ("1 + 2 * 3", 7),
+        ("(1 + 2) * 3", 9),
+        ("2 ^ 3 ^ 2", 512),
+    ] {
+        let expr = definitions.parse(text, crate::defs::Fix::Y).unwrap();
+        assert_eq!(
+            crate::church::numeral(&crate::nbe::normalize(&expr)),
+            Some(num)
+        );
+    }
+}
+
+#[test]
+fn equality() {
+    let mut definitions = crate::defs::Definitions::default();
+    for line in ["eq = a:b:(a b)", "infix 4 == eq"] {
+        assert!(crate::defs::Definitions::is_statement(line));
+        definitions.define(line).unwrap();
+    }
+
+    for line in ["'A == 'B", "a == b", "a ==b"] {
+        assert!(!crate::defs::Definitions::is_statement(line));
+    }
+    let expr = definitions.parse("'A == 'B", crate::defs::Fix::Y).unwrap();
+    assert_eq!(
+        crate::nbe::normalize(&expr),
+        Expr::parse("('A 'B)").unwrap()
+    );
+    assert!(crate::defs::Definitions::is_statement("a ='A"));
+}
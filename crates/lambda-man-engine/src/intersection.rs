@@ -0,0 +1,729 @@
+// Intersection type assignment
+//
+// Strict intersection types, a type is `α` or `σ → φ` where `σ` is an intersection `φ1 ∧ ... ∧ φn`.
+// The empty intersection is `ω`, an argument of type `ω` is not typed at all.
+//
+// x : φi                when x : φ1 ∧ ... ∧ φn
+// λx.M : σ → φ          when M : φ and x : σ
+// M N : φ               when M : φ1 ∧ ... ∧ φn → φ and N : φi for every i
+//
+// A term is strongly normalizing if and only if it has a derivation without `ω`, and it has a
+// normal form if and only if it has a derivation where the type and the free variables have no `ω`.
+//
+// The derivations are found by reducing the term to its normal form, typing the normal form and
+// then going back over the reductions (subject expansion), every copy of an argument gives one
+// type to the intersection. For strong normalization the erased arguments are kept as garbage,
+// `(x:M) N` with `x` not used becomes `[M, N]`, so they are reduced and typed too.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Var(u32),
+    Arrow(Vec<Type>, Box<Type>),
+}
+
+impl Type {
+    fn vars(&self, out: &mut Vec<u32>) {
+        match self {
+            Type::Var(id) => {
+                if !out.contains(id) {
+                    out.push(*id);
+                }
+            }
+            Type::Arrow(from, to) => {
+                for ty in from {
+                    ty.vars(out);
+                }
+                to.vars(out);
+            }
+        }
+    }
+
+    fn format(&self, names: &[u32]) -> String {
+        match self {
+            Type::Var(id) => match names.iter().position(|name| name == id) {
+                Some(i) => greek(i),
+                None => format!("?{id}"),
+            },
+            Type::Arrow(from, to) => {
+                format!(
+                    "{} → {}",
+                    format_intersection(from, names),
+                    to.format(names)
+                )
+            }
+        }
+    }
+}
+
+fn format_intersection(types: &[Type], names: &[u32]) -> String {
+    match types {
+        [] => String::from("ω"),
+        [ty @ Type::Var(_)] => ty.format(names),
+        types => {
+            let types = types
+                .iter()
+                .map(|ty| match ty {
+                    Type::Var(_) => ty.format(names),
+                    Type::Arrow(..) if types.len() == 1 => ty.format(names),
+                    Type::Arrow(..) => format!("({})", ty.format(names)),
+                })
+                .collect::<Vec<_>>();
+            format!("({})", types.join(" ∧ "))
+        }
+    }
+}
+
+fn greek(i: usize) -> String {
+    const NAMES: [char; 12] = ['α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'μ', 'ν'];
+    if i < NAMES.len() {
+        NAMES[i].to_string()
+    } else {
+        format!("{}{}", NAMES[i % NAMES.len()], i / NAMES.len())
+    }
+}
+
+/// A term with binary applications, `Garbage` is an argument that was erased but is kept.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Var(u32),
+    Label(String),
+    Lam(Box<Term>),
+    App(Box<Term>, Box<Term>),
+    Garbage(Box<Term>, Box<Term>),
+}
+
+impl Term {
+    fn from_expr(expr: &Expr) -> Option<Term> {
+        match expr {
+            Expr::Group(exprs) => {
+                let (first, rest) = exprs.split_first()?;
+                let mut out = Term::from_expr(first)?;
+                for expr in rest {
+                    out = Term::App(Box::new(out), Box::new(Term::from_expr(expr)?));
+                }
+                Some(out)
+            }
+            Expr::Def(expr) => Some(Term::Lam(Box::new(Term::from_expr(expr)?))),
+            Expr::Relative(id) => Some(Term::Var(*id)),
+            Expr::Label(name) => Some(Term::Label(name.clone())),
+        }
+    }
+
+    fn to_expr(&self) -> Expr {
+        match self {
+            Term::Var(id) => Expr::Relative(*id),
+            Term::Label(name) => Expr::Label(name.clone()),
+            Term::Lam(body) => Expr::Def(Box::new(body.to_expr())),
+            Term::App(function, argument) => {
+                let mut exprs = match function.to_expr() {
+                    Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                    function => vec![function],
+                };
+                exprs.push(argument.to_expr());
+                Expr::Group(exprs)
+            }
+            Term::Garbage(main, _) => main.to_expr(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Term::Var(_) | Term::Label(_) => 1,
+            Term::Lam(body) => body.size() + 1,
+            Term::App(a, b) | Term::Garbage(a, b) => a.size() + b.size() + 1,
+        }
+    }
+
+    fn uses(&self, at: u32) -> bool {
+        match self {
+            Term::Var(id) => *id == at,
+            Term::Label(_) => false,
+            Term::Lam(body) => body.uses(at + 1),
+            Term::App(a, b) | Term::Garbage(a, b) => a.uses(at) || b.uses(at),
+        }
+    }
+
+    fn shift(&self, by: i64, depth: u32) -> Term {
+        match self {
+            Term::Var(id) if *id >= depth => Term::Var((*id as i64 + by) as u32),
+            Term::Lam(body) => Term::Lam(Box::new(body.shift(by, depth + 1))),
+            Term::App(a, b) => {
+                Term::App(Box::new(a.shift(by, depth)), Box::new(b.shift(by, depth)))
+            }
+            Term::Garbage(a, b) => {
+                Term::Garbage(Box::new(a.shift(by, depth)), Box::new(b.shift(by, depth)))
+            }
+            term => term.clone(),
+        }
+    }
+
+    fn subst(&self, depth: u32, value: &Term) -> Term {
+        match self {
+            Term::Var(id) if *id == depth => value.shift(depth as i64, 0),
+            Term::Var(id) if *id > depth => Term::Var(id - 1),
+            Term::Lam(body) => Term::Lam(Box::new(body.subst(depth + 1, value))),
+            Term::App(a, b) => Term::App(
+                Box::new(a.subst(depth, value)),
+                Box::new(b.subst(depth, value)),
+            ),
+            Term::Garbage(a, b) => Term::Garbage(
+                Box::new(a.subst(depth, value)),
+                Box::new(b.subst(depth, value)),
+            ),
+            term => term.clone(),
+        }
+    }
+
+    /// The path of the leftmost outermost redex, `0` is the first child and `1` the second.
+    fn find_redex(&self, garbage: bool) -> Option<Vec<u8>> {
+        let (path, child) = match self {
+            Term::App(function, _) if matches!(function.as_ref(), Term::Lam(_)) => {
+                return Some(Vec::default());
+            }
+            Term::App(function, _) if garbage && matches!(function.as_ref(), Term::Garbage(..)) => {
+                return Some(Vec::default());
+            }
+            Term::Lam(body) => (0, body.find_redex(garbage)),
+            Term::App(a, b) | Term::Garbage(a, b) => match a.find_redex(garbage) {
+                Some(path) => (0, Some(path)),
+                None => (1, b.find_redex(garbage)),
+            },
+            Term::Var(_) | Term::Label(_) => return None,
+        };
+
+        let mut child = child?;
+        child.insert(0, path);
+        Some(child)
+    }
+
+    fn at_mut(&mut self, path: &[u8]) -> &mut Term {
+        let Some((first, rest)) = path.split_first() else {
+            return self;
+        };
+        match (self, first) {
+            (Term::Lam(body), _) => body.at_mut(rest),
+            (Term::App(a, _) | Term::Garbage(a, _), 0) => a.at_mut(rest),
+            (Term::App(_, b) | Term::Garbage(_, b), _) => b.at_mut(rest),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Reduces the redex, when `garbage` the erased arguments are kept.
+    fn contract(&self, garbage: bool) -> Term {
+        let Term::App(function, argument) = self else {
+            unreachable!()
+        };
+        match function.as_ref() {
+            Term::Lam(body) if garbage && !body.uses(0) => {
+                Term::Garbage(Box::new(body.shift(-1, 0)), argument.clone())
+            }
+            Term::Lam(body) => body.subst(0, argument),
+            // [M, G] N is [(M N), G]
+            Term::Garbage(main, rest) => Term::Garbage(
+                Box::new(Term::App(main.clone(), argument.clone())),
+                rest.clone(),
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Derivation {
+    Var {
+        id: u32,
+        ty: Type,
+    },
+    Label {
+        name: String,
+        ty: Type,
+    },
+    Abs {
+        arg: Vec<Type>,
+        body: Box<Derivation>,
+        ty: Type,
+    },
+    /// The argument is typed once for every type of the intersection.
+    App {
+        function: Box<Derivation>,
+        args: Vec<Derivation>,
+        ty: Type,
+    },
+    /// Only used while building, an erased argument that is kept.
+    Garbage {
+        main: Box<Derivation>,
+        garbage: Box<Derivation>,
+    },
+}
+
+impl Derivation {
+    pub fn ty(&self) -> &Type {
+        match self {
+            Derivation::Var { ty, .. }
+            | Derivation::Label { ty, .. }
+            | Derivation::Abs { ty, .. }
+            | Derivation::App { ty, .. } => ty,
+            Derivation::Garbage { main, .. } => main.ty(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Derivation::Var { .. } | Derivation::Label { .. } => 1,
+            Derivation::Abs { body, .. } => body.size() + 1,
+            Derivation::App { function, args, .. } => {
+                function.size() + args.iter().map(Derivation::size).sum::<usize>() + 1
+            }
+            Derivation::Garbage { main, garbage } => main.size() + garbage.size(),
+        }
+    }
+
+    fn vars(&self, out: &mut Vec<u32>) {
+        self.ty().vars(out);
+        match self {
+            Derivation::Abs { body, .. } => body.vars(out),
+            Derivation::App { function, args, .. } => {
+                function.vars(out);
+                for arg in args {
+                    arg.vars(out);
+                }
+            }
+            Derivation::Garbage { main, garbage } => {
+                main.vars(out);
+                garbage.vars(out);
+            }
+            _ => {}
+        }
+    }
+
+    fn shift(&self, by: i64, depth: u32) -> Derivation {
+        match self {
+            Derivation::Var { id, ty } if *id >= depth => Derivation::Var {
+                id: (*id as i64 + by) as u32,
+                ty: ty.clone(),
+            },
+            Derivation::Abs { arg, body, ty } => Derivation::Abs {
+                arg: arg.clone(),
+                body: Box::new(body.shift(by, depth + 1)),
+                ty: ty.clone(),
+            },
+            Derivation::App { function, args, ty } => Derivation::App {
+                function: Box::new(function.shift(by, depth)),
+                args: args.iter().map(|arg| arg.shift(by, depth)).collect(),
+                ty: ty.clone(),
+            },
+            Derivation::Garbage { main, garbage } => Derivation::Garbage {
+                main: Box::new(main.shift(by, depth)),
+                garbage: Box::new(garbage.shift(by, depth)),
+            },
+            derivation => derivation.clone(),
+        }
+    }
+
+    /// Splits the derivation of `term[value/depth]` into the derivation of `term` and the
+    /// derivations of every copy of `value`.
+    fn split(&self, term: &Term, depth: u32, copies: &mut Vec<Derivation>) -> Derivation {
+        match (term, self) {
+            (Term::Var(id), derivation) if *id == depth => {
+                copies.push(derivation.shift(-(depth as i64), 0));
+                Derivation::Var {
+                    id: depth,
+                    ty: derivation.ty().clone(),
+                }
+            }
+            (Term::Var(id), Derivation::Var { ty, .. }) => Derivation::Var {
+                id: *id,
+                ty: ty.clone(),
+            },
+            (Term::Lam(term), Derivation::Abs { arg, body, ty }) => Derivation::Abs {
+                arg: arg.clone(),
+                body: Box::new(body.split(term, depth + 1, copies)),
+                ty: ty.clone(),
+            },
+            (Term::App(function_term, arg_term), Derivation::App { function, args, ty }) => {
+                Derivation::App {
+                    function: Box::new(function.split(function_term, depth, copies)),
+                    args: args
+                        .iter()
+                        .map(|arg| arg.split(arg_term, depth, copies))
+                        .collect(),
+                    ty: ty.clone(),
+                }
+            }
+            (Term::Garbage(main_term, garbage_term), Derivation::Garbage { main, garbage }) => {
+                Derivation::Garbage {
+                    main: Box::new(main.split(main_term, depth, copies)),
+                    garbage: Box::new(garbage.split(garbage_term, depth, copies)),
+                }
+            }
+            (_, derivation) => derivation.clone(),
+        }
+    }
+
+    /// Turns the derivation of the reduced term into the derivation of `redex` at `path`.
+    fn expand(&mut self, path: &[u8], redex: &Term) {
+        if let Some((first, rest)) = path.split_first() {
+            match (self, first) {
+                (Derivation::Abs { body, .. }, _) => body.expand(rest, redex),
+                (Derivation::App { function, .. }, 0) => function.expand(rest, redex),
+                (Derivation::App { args, .. }, _) => {
+                    for arg in args {
+                        arg.expand(rest, redex);
+                    }
+                }
+                (Derivation::Garbage { main, .. }, 0) => main.expand(rest, redex),
+                (Derivation::Garbage { garbage, .. }, _) => garbage.expand(rest, redex),
+                _ => unreachable!(),
+            }
+            return;
+        }
+
+        let Term::App(function, _) = redex else {
+            unreachable!()
+        };
+
+        *self = match (function.as_ref(), std::mem::replace(self, PLACEHOLDER)) {
+            (Term::Garbage(..), Derivation::Garbage { main, garbage }) => {
+                let Derivation::App { function, args, ty } = *main else {
+                    unreachable!()
+                };
+                Derivation::App {
+                    function: Box::new(Derivation::Garbage {
+                        main: function,
+                        garbage,
+                    }),
+                    args,
+                    ty,
+                }
+            }
+            (Term::Lam(body), Derivation::Garbage { main, garbage }) if !body.uses(0) => {
+                let ty = main.ty().clone();
+                let arg = vec![garbage.ty().clone()];
+                Derivation::App {
+                    function: Box::new(Derivation::Abs {
+                        ty: Type::Arrow(arg.clone(), Box::new(ty.clone())),
+                        arg,
+                        body: Box::new(main.shift(1, 0)),
+                    }),
+                    args: vec![*garbage],
+                    ty,
+                }
+            }
+            (Term::Lam(body), derivation) => {
+                let mut copies = Vec::default();
+                let body = derivation.split(body, 0, &mut copies);
+                let ty = derivation.ty().clone();
+                let arg = copies
+                    .iter()
+                    .map(|copy| copy.ty().clone())
+                    .collect::<Vec<_>>();
+                Derivation::App {
+                    function: Box::new(Derivation::Abs {
+                        ty: Type::Arrow(arg.clone(), Box::new(ty.clone())),
+                        arg,
+                        body: Box::new(body),
+                    }),
+                    args: copies,
+                    ty,
+                }
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    fn format(&self, term: &Term, depth: u32, indent: usize, names: &[u32], out: &mut String) {
+        let text = term.to_expr().format(depth);
+        let ty = self.ty().format(names);
+        out.push_str(&format!("{:indent$}{text} : {ty}\n", ""));
+
+        match (term, self) {
+            (Term::Lam(term), Derivation::Abs { body, .. }) => {
+                body.format(term, depth + 1, indent + 2, names, out)
+            }
+            (Term::App(function_term, arg_term), Derivation::App { function, args, .. }) => {
+                function.format(function_term, depth, indent + 2, names, out);
+                if args.is_empty() {
+                    let text = arg_term.to_expr().format(depth);
+                    out.push_str(&format!("{:indent$}  {text} : ω\n", ""));
+                }
+                for arg in args {
+                    arg.format(arg_term, depth, indent + 2, names, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the rules, `env` has the intersections of the variables that are bound.
+    fn verify(&self, term: &Term, env: &mut Vec<Vec<Type>>, omega: bool) -> bool {
+        match (term, self) {
+            (Term::Var(i), Derivation::Var { id, ty }) => {
+                i == id
+                    && match env.len().checked_sub(*id as usize + 1) {
+                        Some(i) => env[i].contains(ty),
+                        None => true,
+                    }
+            }
+            (Term::Label(a), Derivation::Label { name, .. }) => a == name,
+            (Term::Lam(term), Derivation::Abs { arg, body, ty }) => {
+                if arg.is_empty() && !omega {
+                    return false;
+                }
+                env.push(arg.clone());
+                let valid = body.verify(term, env, omega);
+                env.pop();
+                valid && *ty == Type::Arrow(arg.clone(), Box::new(body.ty().clone()))
+            }
+            (Term::App(function_term, arg_term), Derivation::App { function, args, ty }) => {
+                let Type::Arrow(from, to) = function.ty() else {
+                    return false;
+                };
+                (omega || !args.is_empty())
+                    && **to == *ty
+                    && from.len() == args.len()
+                    && from.iter().zip(args).all(|(from, arg)| from == arg.ty())
+                    && function.verify(function_term, env, omega)
+                    && args.iter().all(|arg| arg.verify(arg_term, env, omega))
+            }
+            _ => false,
+        }
+    }
+}
+
+const PLACEHOLDER: Derivation = Derivation::Var {
+    id: 0,
+    ty: Type::Var(0),
+};
+
+#[derive(Default)]
+struct Fresh(u32);
+
+impl Fresh {
+    fn next(&mut self) -> Type {
+        self.0 += 1;
+        Type::Var(self.0)
+    }
+}
+
+/// Types of the variable `depth` in the derivation.
+fn collect(derivation: &Derivation, depth: u32, out: &mut Vec<Type>) {
+    match derivation {
+        Derivation::Var { id, ty } if *id == depth => out.push(ty.clone()),
+        Derivation::Abs { body, .. } => collect(body, depth + 1, out),
+        Derivation::App { function, args, .. } => {
+            collect(function, depth, out);
+            for arg in args {
+                collect(arg, depth, out);
+            }
+        }
+        Derivation::Garbage { main, garbage } => {
+            collect(main, depth, out);
+            collect(garbage, depth, out);
+        }
+        _ => {}
+    }
+}
+
+/// Types a normal form without `ω`.
+fn type_normal_form(term: &Term, fresh: &mut Fresh) -> Derivation {
+    match term {
+        Term::Var(id) => Derivation::Var {
+            id: *id,
+            ty: fresh.next(),
+        },
+        Term::Label(name) => Derivation::Label {
+            name: name.clone(),
+            ty: fresh.next(),
+        },
+        Term::Lam(body) => {
+            let body = type_normal_form(body, fresh);
+            let mut arg = Vec::default();
+            collect(&body, 0, &mut arg);
+            if arg.is_empty() {
+                arg.push(fresh.next());
+            }
+            Derivation::Abs {
+                ty: Type::Arrow(arg.clone(), Box::new(body.ty().clone())),
+                arg,
+                body: Box::new(body),
+            }
+        }
+        Term::Garbage(main, garbage) => Derivation::Garbage {
+            main: Box::new(type_normal_form(main, fresh)),
+            garbage: Box::new(type_normal_form(garbage, fresh)),
+        },
+        Term::App(..) => {
+            let mut args = Vec::default();
+            let mut head = term;
+            while let Term::App(function, argument) = head {
+                args.push(type_normal_form(argument, fresh));
+                head = function;
+            }
+
+            let result = fresh.next();
+            let mut ty = result.clone();
+            for arg in args.iter() {
+                ty = Type::Arrow(vec![arg.ty().clone()], Box::new(ty));
+            }
+
+            let mut out = match head {
+                Term::Var(id) => Derivation::Var { id: *id, ty },
+                Term::Label(name) => Derivation::Label {
+                    name: name.clone(),
+                    ty,
+                },
+                _ => unreachable!("The term is not in normal form"),
+            };
+
+            for arg in args.into_iter().rev() {
+                let Type::Arrow(_, to) = out.ty().clone() else {
+                    unreachable!()
+                };
+                out = Derivation::App {
+                    function: Box::new(out),
+                    args: vec![arg],
+                    ty: *to,
+                };
+            }
+
+            out
+        }
+    }
+}
+
+/// Reduces the term to normal form and builds the derivation back, `None` if it takes too long.
+fn derive(term: &Term, garbage: bool, bounds: Bounds) -> Option<Derivation> {
+    let mut steps = Vec::default();
+    let mut current = term.clone();
+
+    while let Some(path) = current.find_redex(garbage) {
+        if steps.len() >= bounds.steps || current.size() > bounds.size {
+            return None;
+        }
+
+        let redex = current.at_mut(&path);
+        let reduced = redex.contract(garbage);
+        steps.push((path, std::mem::replace(redex, reduced)));
+    }
+
+    let mut derivation = type_normal_form(&current, &mut Fresh::default());
+
+    for (path, redex) in steps.iter().rev() {
+        derivation.expand(path, redex);
+        if derivation.size() > bounds.size {
+            return None;
+        }
+    }
+
+    Some(derivation)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    /// How many reductions can be used to find the normal form.
+    pub steps: usize,
+    /// How big the terms and the derivations can be.
+    pub size: usize,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            steps: 1000,
+            size: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// Every reduction order ends, the derivation has no `ω`.
+    Strong,
+    /// The term has a normal form, strong normalization was not found within the bounds.
+    Weak,
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub normalization: Normalization,
+    pub derivation: Derivation,
+    term: Term,
+}
+
+impl Certificate {
+    /// Formats the derivation as a tree, every line is a term and its type.
+    pub fn format(&self) -> String {
+        let mut names = Vec::default();
+        self.derivation.vars(&mut names);
+
+        let mut out = String::default();
+        self.derivation.format(&self.term, 0, 0, &names, &mut out);
+        out
+    }
+
+    /// Checks that the derivation follows the rules.
+    pub fn verify(&self) -> bool {
+        let omega = self.normalization == Normalization::Weak;
+        self.derivation
+            .verify(&self.term, &mut Vec::default(), omega)
+    }
+}
+
+/// Searches a derivation that shows that the term is normalizing.
+pub fn certify(expr: &Expr, bounds: Bounds) -> Option<Certificate> {
+    let term = Term::from_expr(expr)?;
+
+    if let Some(derivation) = derive(&term, true, bounds) {
+        return Some(Certificate {
+            normalization: Normalization::Strong,
+            derivation,
+            term,
+        });
+    }
+
+    let derivation = derive(&term, false, bounds)?;
+    Some(Certificate {
+        normalization: Normalization::Weak,
+        derivation,
+        term,
+    })
+}
+
+#[test]
+fn strong() {
+    for text in [
+        "a:a",
+        "((a:(a a)) (a:a))",
+        // 2 2
+        "((a:b:(a (a b))) (a:b:(a (a b))))",
+        // (a:b:b) is erasing the argument
+        "(x:((a:b:b) (x x) x))",
+    ] {
+        let certificate = certify(&Expr::parse(text).unwrap(), Bounds::default()).unwrap();
+        println!("{}", certificate.format());
+        assert_eq!(certificate.normalization, Normalization::Strong);
+        assert!(certificate.verify());
+    }
+
+    let certificate = certify(
+        &Expr::parse("((a:(a a)) (a:a))").unwrap(),
+        Bounds::default(),
+    );
+    assert_eq!(certificate.unwrap().derivation.ty().format(&[1]), "α → α");
+}
+
+#[test]
+fn weak() {
+    // (a:b:b) erases Ω that has no normal form
+    let expr = Expr::parse("((a:b:b) ((a:(a a)) (a:(a a))))").unwrap();
+    let certificate = certify(&expr, Bounds::default()).unwrap();
+    println!("{}", certificate.format());
+    assert_eq!(certificate.normalization, Normalization::Weak);
+    assert!(certificate.verify());
+    assert!(certificate.format().contains("ω"));
+
+    let expr = Expr::parse("((a:(a a)) (a:(a a)))").unwrap();
+    assert!(certify(&expr, Bounds::default()).is_none());
+}
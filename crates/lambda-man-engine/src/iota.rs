@@ -0,0 +1,153 @@
+// [Iota and Jot](https://en.wikipedia.org/wiki/Iota_and_Jot)
+//
+// ι is f:(f S K), `i` is ι and `*` applies the next two terms.
+// ii is I, i(i(ii)) is K and i(i(i(ii))) is S.
+//
+// A Jot program starts as I, `0` applies it to S and K, `1` turns `w` into a:b:(w (a b)).
+// `1 F A` is (F A), 11100 is K and 11111000 is S.
+
+use crate::{
+    Expr,
+    ski::{Abstraction, Comb, Term},
+};
+
+fn iota() -> Expr {
+    Expr::Def(Box::new(Expr::Group(vec![
+        Expr::Relative(0),
+        Comb::S.to_expr(),
+        Comb::K.to_expr(),
+    ])))
+}
+
+fn ski(expr: &Expr) -> Option<Term> {
+    let term = Term::from_expr(expr, Abstraction::Naive)?;
+    if term.is_closed() {
+        Some(term)
+    } else {
+        eprintln!("Only closed terms without labels can be encoded");
+        None
+    }
+}
+
+/// Encodes a closed term as Iota, `a:a` is `*ii`.
+pub fn to_iota(expr: &Expr) -> Option<String> {
+    fn encode(term: &Term, out: &mut String) {
+        match term {
+            Term::App(function, argument) => {
+                out.push('*');
+                encode(function, out);
+                encode(argument, out);
+            }
+            Term::Comb(Comb::I) => out.push_str("*ii"),
+            Term::Comb(Comb::K) => out.push_str("*i*i*ii"),
+            Term::Comb(Comb::S) => out.push_str("*i*i*i*ii"),
+            _ => unreachable!(),
+        }
+    }
+
+    let mut out = String::default();
+    encode(&ski(expr)?, &mut out);
+    Some(out)
+}
+
+/// Decodes Iota, whitespace is ignored.
+pub fn from_iota(text: &str) -> Option<Expr> {
+    fn decode(chars: &mut impl Iterator<Item = char>) -> Option<Expr> {
+        match chars.next()? {
+            'i' => Some(iota()),
+            '*' => Some(Expr::Group(vec![decode(chars)?, decode(chars)?])),
+            ch => {
+                eprintln!("Unexpected `{ch}` in Iota");
+                None
+            }
+        }
+    }
+
+    let mut chars = text.chars().filter(|ch| !ch.is_whitespace());
+    let expr = decode(&mut chars)?;
+    chars.next().is_none().then_some(expr)
+}
+
+/// Encodes a closed term as Jot.
+pub fn to_jot(expr: &Expr) -> Option<String> {
+    fn encode(term: &Term, out: &mut String) {
+        match term {
+            Term::App(function, argument) => {
+                out.push('1');
+                encode(function, out);
+                encode(argument, out);
+            }
+            Term::Comb(Comb::I) => {
+                let skk = Term::app(
+                    Term::app(Term::Comb(Comb::S), Term::Comb(Comb::K)),
+                    Term::Comb(Comb::K),
+                );
+                encode(&skk, out);
+            }
+            Term::Comb(Comb::K) => out.push_str("11100"),
+            Term::Comb(Comb::S) => out.push_str("11111000"),
+            _ => unreachable!(),
+        }
+    }
+
+    let mut out = String::default();
+    encode(&ski(expr)?, &mut out);
+    Some(out)
+}
+
+/// Decodes Jot, every string of `0` and `1` is a program, whitespace is ignored.
+pub fn from_jot(text: &str) -> Option<Expr> {
+    let mut out = Comb::I.to_expr();
+    for ch in text.chars().filter(|ch| !ch.is_whitespace()) {
+        out = match ch {
+            '0' => Expr::Group(vec![out, Comb::S.to_expr(), Comb::K.to_expr()]),
+            // `out` is closed so it does not need to be updated.
+            '1' => Expr::Def(Box::new(Expr::Def(Box::new(Expr::Group(vec![
+                out,
+                Expr::Group(vec![Expr::Relative(1), Expr::Relative(0)]),
+            ]))))),
+            ch => {
+                eprintln!("Unexpected `{ch}` in Jot");
+                return None;
+            }
+        };
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+fn normal_form(expr: Expr) -> Term {
+    let expr = Expr::Group(vec![expr, Expr::Label("x".into()), Expr::Label("y".into())]);
+    let expr = crate::reduce::normalize(&expr, crate::reduce::Strategy::Innermost);
+    Term::from_expr(&expr, Abstraction::Naive).unwrap()
+}
+
+#[test]
+fn same_normal_form() {
+    assert_eq!(to_iota(&Expr::parse("a:a").unwrap()).unwrap(), "*ii");
+
+    // true, false, swap and 2
+    for text in ["a:b:a", "a:b:b", "a:b:(b a)", "a:b:(a (a b))"] {
+        let expr = Expr::parse(text).unwrap();
+        let expected = normal_form(expr.clone());
+
+        let iota = from_iota(&to_iota(&expr).unwrap()).unwrap();
+        assert_eq!(normal_form(iota), expected);
+
+        let jot = from_jot(&to_jot(&expr).unwrap()).unwrap();
+        assert_eq!(normal_form(jot), expected);
+
+        let bckw = Term::from_expr(&expr, Abstraction::Bckw).unwrap();
+        assert_eq!(normal_form(bckw.to_expr()), expected);
+    }
+
+    // K and S written by hand
+    for (iota, jot, comb) in [
+        ("*i*i*ii", "11100", Comb::K),
+        ("*i*i*i*ii", "11111000", Comb::S),
+    ] {
+        let expected = normal_form(comb.to_expr());
+        assert_eq!(normal_form(from_iota(iota).unwrap()), expected);
+        assert_eq!(normal_form(from_jot(jot).unwrap()), expected);
+    }
+}
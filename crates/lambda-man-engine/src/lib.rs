@@ -15,6 +15,29 @@
 // f:x:f (f (f (f (f (f (f (f (f x) is 9
 // n:f:x:f (n f x) is succ
 
+pub mod blc;
+pub mod church;
+pub mod data;
+pub mod defs;
+pub mod graph;
+pub mod infix;
+pub mod intersection;
+pub mod iota;
+pub mod machine;
+pub mod modules;
+pub mod nbe;
+pub mod optimal;
+pub mod pcf;
+pub mod prim;
+pub mod quote;
+pub mod reduce;
+pub mod ski;
+pub mod store;
+pub mod subst;
+pub mod trace;
+pub mod typed;
+pub mod types;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Group(Vec<Expr>),
@@ -23,15 +46,31 @@ pub enum Expr {
     Label(String),
 }
 
+/// How variables are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notation {
+    /// `a:b:(a b)`
+    #[default]
+    Names,
+    /// `λ λ (1 0)`
+    DeBruijn,
+    /// `a:b:(a#1 b#0)`
+    Both,
+}
+
 impl Expr {
     pub fn format(&self, depth: u32) -> String {
+        self.format_with(depth, Notation::Names)
+    }
+
+    pub fn format_with(&self, depth: u32, notation: Notation) -> String {
         let mut out = String::default();
 
         match self {
             Expr::Group(exprs) => {
                 out.push('(');
                 for (i, expr) in exprs.iter().enumerate() {
-                    out.push_str(&expr.format(depth));
+                    out.push_str(&expr.format_with(depth, notation));
                     if i != exprs.len() - 1 {
                         out.push(' ');
                     }
@@ -39,16 +78,15 @@ impl Expr {
                 out.push(')');
             }
             Expr::Def(expr) => {
-                out.push_str(&format!("{}:", var_name(depth)));
-                out.push_str(&expr.format(depth + 1));
-            }
-            Expr::Relative(id) => {
-                if let Some(id) = depth.checked_sub(*id + 1) {
-                    out.push_str(&var_name(id));
-                } else {
-                    out.push_str(&format!("!{id}!"));
-                }
+                out.push_str(&format_def(depth, notation));
+                out.push_str(&expr.format_with(depth + 1, notation));
             }
+            Expr::Relative(id) => match (depth.checked_sub(*id + 1), notation) {
+                (_, Notation::DeBruijn) => out.push_str(&id.to_string()),
+                (Some(name), Notation::Names) => out.push_str(&var_name(name)),
+                (Some(name), Notation::Both) => out.push_str(&format!("{}#{id}", var_name(name))),
+                (None, _) => out.push_str(&format!("!{id}!")),
+            },
             Expr::Label(name) => {
                 out.push('\'');
                 out.push_str(name);
@@ -58,11 +96,44 @@ impl Expr {
         out
     }
 
+    /// The longest start of the path `at` that is in the expression, a path into a part that was
+    /// replaced with a label ends at the label.
+    pub fn valid_path<'a>(&self, at: &'a [usize]) -> &'a [usize] {
+        let mut expr = self;
+        for (len, i) in at.iter().enumerate() {
+            while let Expr::Def(body) = expr {
+                expr = body;
+            }
+            match expr {
+                Expr::Group(exprs) if *i < exprs.len() => expr = &exprs[*i],
+                _ => return &at[..len],
+            }
+        }
+        at
+    }
+
     pub fn format_highlightd(&self, depth: u32, at: &[usize], ansi_color: &str) -> String {
-        self._format_highlight(depth, vec![], at, ansi_color)
+        self.format_highlightd_with(depth, at, ansi_color, Notation::Names)
+    }
+
+    pub fn format_highlightd_with(
+        &self,
+        depth: u32,
+        at: &[usize],
+        ansi_color: &str,
+        notation: Notation,
+    ) -> String {
+        self._format_highlight(depth, vec![], at, ansi_color, notation)
     }
 
-    fn _format_highlight(&self, depth: u32, path: Vec<usize>, at: &[usize], ansi: &str) -> String {
+    fn _format_highlight(
+        &self,
+        depth: u32,
+        path: Vec<usize>,
+        at: &[usize],
+        ansi: &str,
+        notation: Notation,
+    ) -> String {
         let mut out = String::default();
 
         let highlighted = path == at;
@@ -80,7 +151,7 @@ impl Expr {
                 for (i, expr) in exprs.iter().enumerate() {
                     let mut path = path.clone();
                     path.push(i);
-                    out.push_str(&expr._format_highlight(depth, path, at, ansi));
+                    out.push_str(&expr._format_highlight(depth, path, at, ansi, notation));
                     if i != exprs.len() - 1 {
                         out.push(' ');
                     }
@@ -88,11 +159,11 @@ impl Expr {
                 out.push(')');
             }
             Expr::Def(expr) => {
-                out.push_str(&format!("{}:", var_name(depth)));
-                out.push_str(&expr._format_highlight(depth + 1, path.clone(), at, ansi));
+                out.push_str(&format_def(depth, notation));
+                out.push_str(&expr._format_highlight(depth + 1, path.clone(), at, ansi, notation));
             }
             _ => {
-                out.push_str(&self.format(depth));
+                out.push_str(&self.format_with(depth, notation));
             }
         }
 
@@ -167,7 +238,8 @@ impl Expr {
                     }
                     ')' | '(' => break,
                     _ => {
-                        if ch.is_alphabetic() {
+                        // `.` is in the names of other modules, `lists.map`
+                        if ch.is_alphabetic() || ch == '.' {
                             name.push(ch);
                         }
                         *i += 1;
@@ -206,6 +278,76 @@ impl Expr {
         None
     }
 
+    /// Parses the de Bruijn notation, `λ λ (1 0)` or `\ \ (1 0)`.
+    pub fn parse_de_bruijn(text: &str) -> Option<Self> {
+        let chars = text.chars().collect::<Vec<char>>();
+        let mut error = false;
+        let res = Self::_parse_de_bruijn(&chars, &mut 0, &mut error, 0)?;
+        (!error).then_some(res)
+    }
+
+    fn _parse_de_bruijn(
+        text: &[char],
+        i: &mut usize,
+        error: &mut bool,
+        depth: u32,
+    ) -> Option<Self> {
+        let ch = *text.get(*i)?;
+        *i += 1;
+
+        if ch.is_whitespace() {
+            return Self::_parse_de_bruijn(text, i, error, depth);
+        }
+
+        if ch == 'λ' || ch == '\\' {
+            let e = Self::_parse_de_bruijn(text, i, error, depth + 1)?;
+            return Some(Expr::Def(Box::new(e)));
+        }
+
+        if ch == '\'' || ch.is_ascii_digit() {
+            let mut name = String::default();
+            if ch != '\'' {
+                name.push(ch);
+            }
+
+            while let Some(ch) = text.get(*i) {
+                if ch.is_whitespace() || *ch == '(' || *ch == ')' {
+                    break;
+                }
+                name.push(*ch);
+                *i += 1;
+            }
+
+            if ch == '\'' {
+                return Some(Expr::Label(name));
+            }
+
+            if let Ok(id) = name.parse::<u32>()
+                && id < depth
+            {
+                return Some(Expr::Relative(id));
+            }
+
+            *error = true;
+
+            eprintln!("{}", text.iter().collect::<String>());
+            eprintln!("{:1$}^", " ", *i - 1);
+            eprintln!("`{name}` is not bound, depth is {depth}");
+
+            return None;
+        }
+
+        if ch == '(' {
+            let mut exprs = Vec::default();
+            while let Some(expr) = Self::_parse_de_bruijn(text, i, error, depth) {
+                exprs.push(expr);
+            }
+            return Some(Expr::Group(exprs));
+        }
+
+        None
+    }
+
     pub fn replace_relative(&mut self, depth: u32, value: Expr) {
         match self {
             Expr::Group(exprs) => {
@@ -285,6 +427,57 @@ impl Expr {
         }
     }
 
+    /// The number of functions, variables and labels.
+    pub fn size(&self) -> usize {
+        match self {
+            Expr::Group(exprs) => exprs.iter().map(Expr::size).sum(),
+            Expr::Def(expr) => 1 + expr.size(),
+            Expr::Relative(_) | Expr::Label(_) => 1,
+        }
+    }
+
+    /// Compares two expressions without looking at how the applications are grouped,
+    /// Removes the groups that have only one element.
+    pub fn unwrap(&self) -> &Expr {
+        let mut expr = self;
+        while let Expr::Group(exprs) = expr {
+            if exprs.len() != 1 {
+                break;
+            }
+            expr = &exprs[0];
+        }
+        expr
+    }
+
+    /// `((a b) c)` is the same as `(a b c)`.
+    pub fn same(&self, other: &Expr) -> bool {
+        let (a, b) = (self.spine(), other.spine());
+        if a.len() != b.len() {
+            return false;
+        }
+        if a.len() > 1 {
+            return a.iter().zip(&b).all(|(a, b)| a.same(b));
+        }
+
+        match (a[0], b[0]) {
+            (Expr::Def(a), Expr::Def(b)) => a.same(b),
+            (Expr::Group(a), Expr::Group(b)) => a.is_empty() && b.is_empty(),
+            (a, b) => a == b,
+        }
+    }
+
+    /// The function and the arguments that it is applied to.
+    pub fn spine(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Group(exprs) if !exprs.is_empty() => {
+                let mut spine = exprs[0].spine();
+                spine.extend(&exprs[1..]);
+                spine
+            }
+            _ => vec![self],
+        }
+    }
+
     pub fn find_beta_reductions(&self) -> Vec<(u32, Vec<usize>)> {
         self._find_beta_reductions(vec![], 0, vec![])
     }
@@ -378,10 +571,7 @@ impl Expr {
         let mut ati = at.iter();
         {
             let mut index = ati.next().copied();
-            loop {
-                let Some(i) = index else {
-                    break;
-                };
+            while let Some(i) = index {
                 match unsafe {
                     std::mem::transmute::<&mut Expr, &mut Expr>(*tree.last_mut().unwrap())
                 } {
@@ -457,6 +647,11 @@ impl Expr {
 
         true
     }
+
+    /// The beta reductions until the normal form, see `reduce`.
+    pub fn reductions(&self, strategy: reduce::Strategy) -> reduce::Reductions {
+        reduce::Reductions::new(self.clone(), strategy)
+    }
 }
 
 impl From<usize> for Expr {
@@ -485,6 +680,13 @@ impl From<usize> for Expr {
     }
 }
 
+fn format_def(depth: u32, notation: Notation) -> String {
+    match notation {
+        Notation::DeBruijn => String::from("λ "),
+        Notation::Names | Notation::Both => format!("{}:", var_name(depth)),
+    }
+}
+
 fn var_name(id: u32) -> String {
     match id {
         0 => String::from("a"),
@@ -580,30 +782,13 @@ fn succ() {
     ))))));
     let mut expr = Expr::Group(vec![Expr::Group(vec![succ.clone()]), Expr::from(1)]);
 
-    loop {
-        println!("{}", expr.format(0));
-
-        let betas = expr.find_beta_reductions();
-        eprintln!("{betas:?}");
-
-        if betas.is_empty() {
-            break;
-        }
-
-        let mut max_i = 0;
-        let mut last_score = 0;
-        for (i, (score, _)) in betas.iter().enumerate() {
-            if *score > last_score {
-                last_score = *score;
-                max_i = i;
-            }
-        }
-
-        expr.beta_reduction_at(&betas[max_i].1);
+    println!("{}", expr.format(0));
+    for reduction in expr.reductions(reduce::Strategy::Innermost) {
+        eprintln!("{:?}", reduction.at());
+        println!("{}", reduction.expr.format(0));
+        expr = reduction.expr;
     }
 
-    expr.simplify();
-
     assert_eq!(expr, Expr::from(2))
 }
 
@@ -635,31 +820,30 @@ fn add() {
         Expr::from(2),
     ]);
 
-    loop {
-        println!("{}", expr.format(0));
-
-        let betas = expr.find_beta_reductions();
-        eprintln!("{betas:?}");
-
-        if betas.is_empty() {
-            break;
-        }
-
-        let mut max_i = 0;
-        let mut last_score = 0;
-        for (i, (score, _)) in betas.iter().enumerate() {
-            if *score > last_score {
-                last_score = *score;
-                max_i = i;
-            }
-        }
-
-        expr.beta_reduction_at(&betas[max_i].1);
+    println!("{}", expr.format(0));
+    for reduction in expr.reductions(reduce::Strategy::Innermost) {
+        eprintln!("{:?}", reduction.at());
+        println!("{}", reduction.expr.format(0));
+        expr = reduction.expr;
     }
 
-    expr.simplify();
-
     println!("F: {}", expr.format(0));
 
     assert_eq!(expr, Expr::parse("(f:x:(f(f(f(f x)))))").unwrap(),)
 }
+
+#[test]
+fn de_bruijn() {
+    let expr = Expr::parse("a:b:(a (b))").unwrap();
+    assert_eq!(expr.format_with(0, Notation::DeBruijn), "λ λ (1 (0))");
+    assert_eq!(expr.format_with(0, Notation::Both), "a:b:(a#1 (b#0))");
+    assert_eq!(Expr::parse_de_bruijn("λ λ (1 (0))").unwrap(), expr);
+    assert_eq!(Expr::parse_de_bruijn("\\ \\(1(0))").unwrap(), expr);
+
+    // add 1 2
+    let expr = Expr::parse("(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))").unwrap();
+    let text = expr.format_with(0, Notation::DeBruijn);
+    assert_eq!(Expr::parse_de_bruijn(&text).unwrap(), expr);
+
+    assert_eq!(Expr::parse_de_bruijn("λ 1"), None);
+}
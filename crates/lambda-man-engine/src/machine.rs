@@ -0,0 +1,541 @@
+// Abstract machines
+//
+// Krivine is call-by-name, the arguments are pushed on the stack as closures and a function takes
+// the top of the stack. CEK is call-by-value, the continuation remembers what to do after the
+// current term is a value. SECD compiles an application to its parts and `ap`, a call saves the
+// stack, environment and control on the dump.
+//
+// The machines stop when the term is a function without arguments, so the result is not the normal
+// form. The environment is shown from the outermost variable, `a=` is the first variable.
+
+use std::rc::Rc;
+
+use crate::{Expr, Notation};
+
+struct Node<T> {
+    value: T,
+    next: Env<T>,
+}
+
+type Env<T> = Option<Rc<Node<T>>>;
+
+fn push<T>(env: &Env<T>, value: T) -> Env<T> {
+    Some(Rc::new(Node {
+        value,
+        next: env.clone(),
+    }))
+}
+
+fn get<T>(env: &Env<T>, id: u32) -> Option<&T> {
+    let mut node = env.as_ref();
+    for _ in 0..id {
+        node = node?.next.as_ref();
+    }
+    node.map(|node| &node.value)
+}
+
+/// The values of the environment, the innermost is first.
+fn values<T>(env: &Env<T>) -> Vec<&T> {
+    let mut out = Vec::default();
+    let mut node = env.as_ref();
+    while let Some(n) = node {
+        out.push(&n.value);
+        node = n.next.as_ref();
+    }
+    out
+}
+
+/// Replaces the variables that are not bound inside of `expr` with `env`.
+fn substitute(expr: &Expr, depth: u32, env: &[Expr]) -> Expr {
+    match expr {
+        Expr::Group(exprs) => Expr::Group(
+            exprs
+                .iter()
+                .map(|expr| substitute(expr, depth, env))
+                .collect(),
+        ),
+        Expr::Def(body) => Expr::Def(Box::new(substitute(body, depth + 1, env))),
+        Expr::Relative(id) if *id >= depth => match env.get((id - depth) as usize) {
+            Some(value) => value.clone(),
+            None => expr.clone(),
+        },
+        _ => expr.clone(),
+    }
+}
+
+fn format_env(env: &[Expr], notation: Notation) -> String {
+    let len = env.len() as u32;
+    let mut out = String::from("[");
+    for (i, value) in env.iter().enumerate().rev() {
+        out.push_str(&Expr::Relative(i as u32).format_with(len, notation));
+        out.push('=');
+        out.push_str(&value.format_with(0, notation));
+        if i != 0 {
+            out.push_str(", ");
+        }
+    }
+    out.push(']');
+    out
+}
+
+fn format_list(exprs: impl Iterator<Item = Expr>, notation: Notation) -> String {
+    let exprs = exprs
+        .map(|expr| expr.format_with(0, notation))
+        .collect::<Vec<String>>();
+    format!("[{}]", exprs.join(", "))
+}
+
+fn apply(function: Expr, args: impl Iterator<Item = Expr>) -> Expr {
+    let mut exprs = vec![function];
+    exprs.extend(args);
+    if exprs.len() == 1 {
+        exprs.remove(0)
+    } else {
+        Expr::Group(exprs)
+    }
+}
+
+#[derive(Clone)]
+struct Closure<'a> {
+    expr: &'a Expr,
+    env: Env<Closure<'a>>,
+}
+
+impl Closure<'_> {
+    fn env(&self) -> Vec<Expr> {
+        values(&self.env)
+            .into_iter()
+            .map(|closure| closure.to_expr())
+            .collect()
+    }
+
+    fn to_expr(&self) -> Expr {
+        substitute(self.expr, 0, &self.env())
+    }
+}
+
+/// Call-by-name
+pub struct Krivine<'a> {
+    term: &'a Expr,
+    env: Env<Closure<'a>>,
+    stack: Vec<Closure<'a>>,
+}
+
+impl<'a> Krivine<'a> {
+    pub fn new(expr: &'a Expr) -> Self {
+        Self {
+            term: expr,
+            env: None,
+            stack: Vec::default(),
+        }
+    }
+
+    /// Returns `false` when the machine has stopped.
+    pub fn step(&mut self) -> bool {
+        match self.term {
+            Expr::Group(exprs) => {
+                let Some((function, args)) = exprs.split_first() else {
+                    return false;
+                };
+                for arg in args.iter().rev() {
+                    self.stack.push(Closure {
+                        expr: arg,
+                        env: self.env.clone(),
+                    });
+                }
+                self.term = function;
+            }
+            Expr::Def(body) => {
+                let Some(arg) = self.stack.pop() else {
+                    return false;
+                };
+                self.env = push(&self.env, arg);
+                self.term = body;
+            }
+            Expr::Relative(id) => {
+                let Some(closure) = get(&self.env, *id).cloned() else {
+                    return false;
+                };
+                self.term = closure.expr;
+                self.env = closure.env;
+            }
+            Expr::Label(_) => return false,
+        }
+        true
+    }
+
+    /// The term, the environment and the stack, the top of the stack is first.
+    pub fn format(&self, notation: Notation) -> String {
+        let env = values(&self.env)
+            .into_iter()
+            .map(|closure| closure.to_expr())
+            .collect::<Vec<Expr>>();
+        format!(
+            "{}\t{}\t{}",
+            self.term.format_with(env.len() as u32, notation),
+            format_env(&env, notation),
+            format_list(self.stack.iter().rev().map(Closure::to_expr), notation)
+        )
+    }
+
+    pub fn result(&self) -> Expr {
+        let closure = Closure {
+            expr: self.term,
+            env: self.env.clone(),
+        };
+        apply(
+            closure.to_expr(),
+            self.stack.iter().rev().map(Closure::to_expr),
+        )
+    }
+}
+
+#[derive(Clone)]
+enum Value<'a> {
+    /// The body of a function.
+    Closure(&'a Expr, Env<Value<'a>>),
+    /// A label or `()` with its arguments.
+    Neutral(&'a Expr, Vec<Value<'a>>),
+}
+
+impl Value<'_> {
+    fn to_expr(&self) -> Expr {
+        match self {
+            Value::Closure(body, env) => Expr::Def(Box::new(substitute(body, 1, &to_exprs(env)))),
+            Value::Neutral(head, args) => apply((*head).clone(), args.iter().map(Value::to_expr)),
+        }
+    }
+}
+
+fn to_exprs(env: &Env<Value>) -> Vec<Expr> {
+    values(env).into_iter().map(Value::to_expr).collect()
+}
+
+/// Evaluates the terms that do not need a machine step.
+fn value<'a>(expr: &'a Expr, env: &Env<Value<'a>>) -> Option<Value<'a>> {
+    match expr {
+        Expr::Group(exprs) if exprs.is_empty() => Some(Value::Neutral(expr, Vec::default())),
+        Expr::Label(_) => Some(Value::Neutral(expr, Vec::default())),
+        Expr::Def(body) => Some(Value::Closure(body, env.clone())),
+        Expr::Relative(id) => get(env, *id).cloned(),
+        Expr::Group(_) => None,
+    }
+}
+
+enum Control<'a> {
+    Eval(&'a Expr, Env<Value<'a>>),
+    Value(Value<'a>),
+}
+
+enum Frame<'a> {
+    /// Evaluate the argument after the function.
+    Arg(&'a Expr, Env<Value<'a>>),
+    /// Call the function with the value.
+    Call(Value<'a>),
+}
+
+/// Call-by-value
+pub struct Cek<'a> {
+    control: Control<'a>,
+    continuation: Vec<Frame<'a>>,
+}
+
+impl<'a> Cek<'a> {
+    pub fn new(expr: &'a Expr) -> Self {
+        Self {
+            control: Control::Eval(expr, None),
+            continuation: Vec::default(),
+        }
+    }
+
+    /// Returns `false` when the machine has stopped.
+    pub fn step(&mut self) -> bool {
+        match &mut self.control {
+            Control::Eval(Expr::Group(exprs), env) if !exprs.is_empty() => {
+                let (function, args) = exprs.split_first().unwrap();
+                for arg in args.iter().rev() {
+                    self.continuation.push(Frame::Arg(arg, env.clone()));
+                }
+                self.control = Control::Eval(function, env.take());
+            }
+            Control::Eval(expr, env) => {
+                let Some(value) = value(expr, env) else {
+                    return false;
+                };
+                self.control = Control::Value(value);
+            }
+            Control::Value(value) => match self.continuation.pop() {
+                None => return false,
+                Some(Frame::Arg(arg, env)) => {
+                    self.continuation.push(Frame::Call(value.clone()));
+                    self.control = Control::Eval(arg, env);
+                }
+                Some(Frame::Call(Value::Closure(body, env))) => {
+                    self.control = Control::Eval(body, push(&env, value.clone()));
+                }
+                Some(Frame::Call(Value::Neutral(head, mut args))) => {
+                    args.push(value.clone());
+                    self.control = Control::Value(Value::Neutral(head, args));
+                }
+            },
+        }
+        true
+    }
+
+    /// The control, the environment and the continuation, the next frame is first.
+    pub fn format(&self, notation: Notation) -> String {
+        let (control, env) = match &self.control {
+            Control::Eval(expr, env) => {
+                let env = to_exprs(env);
+                (expr.format_with(env.len() as u32, notation), env)
+            }
+            Control::Value(value) => (
+                format!("<{}>", value.to_expr().format_with(0, notation)),
+                Vec::default(),
+            ),
+        };
+
+        let frames = self
+            .continuation
+            .iter()
+            .rev()
+            .map(|frame| match frame {
+                Frame::Arg(arg, env) => {
+                    let env = to_exprs(env);
+                    format!("arg {}", substitute(arg, 0, &env).format_with(0, notation))
+                }
+                Frame::Call(function) => {
+                    format!("call {}", function.to_expr().format_with(0, notation))
+                }
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            "{control}\t{}\t[{}]",
+            format_env(&env, notation),
+            frames.join(", ")
+        )
+    }
+
+    pub fn result(&self) -> Expr {
+        let mut expr = match &self.control {
+            Control::Eval(expr, env) => substitute(expr, 0, &to_exprs(env)),
+            Control::Value(value) => value.to_expr(),
+        };
+
+        for frame in self.continuation.iter().rev() {
+            expr = match frame {
+                Frame::Arg(arg, env) => {
+                    apply(expr, [substitute(arg, 0, &to_exprs(env))].into_iter())
+                }
+                Frame::Call(function) => apply(function.to_expr(), [expr].into_iter()),
+            };
+        }
+        expr
+    }
+}
+
+enum Instruction<'a> {
+    Term(&'a Expr),
+    Apply,
+}
+
+struct Dump<'a> {
+    stack: Vec<Value<'a>>,
+    env: Env<Value<'a>>,
+    control: Vec<Instruction<'a>>,
+}
+
+/// Stack, environment, control and dump
+pub struct Secd<'a> {
+    stack: Vec<Value<'a>>,
+    env: Env<Value<'a>>,
+    /// The next instruction is last.
+    control: Vec<Instruction<'a>>,
+    dump: Vec<Dump<'a>>,
+}
+
+impl<'a> Secd<'a> {
+    pub fn new(expr: &'a Expr) -> Self {
+        Self {
+            stack: Vec::default(),
+            env: None,
+            control: vec![Instruction::Term(expr)],
+            dump: Vec::default(),
+        }
+    }
+
+    /// Returns `false` when the machine has stopped.
+    pub fn step(&mut self) -> bool {
+        match self.control.pop() {
+            Some(Instruction::Term(Expr::Group(exprs))) if !exprs.is_empty() => {
+                let (function, args) = exprs.split_first().unwrap();
+                for arg in args.iter().rev() {
+                    self.control.push(Instruction::Apply);
+                    self.control.push(Instruction::Term(arg));
+                }
+                self.control.push(Instruction::Term(function));
+            }
+            Some(Instruction::Term(expr)) => {
+                let Some(value) = value(expr, &self.env) else {
+                    self.control.push(Instruction::Term(expr));
+                    return false;
+                };
+                self.stack.push(value);
+            }
+            Some(Instruction::Apply) => {
+                let (Some(arg), Some(function)) = (self.stack.pop(), self.stack.pop()) else {
+                    return false;
+                };
+                match function {
+                    Value::Closure(body, env) => {
+                        self.dump.push(Dump {
+                            stack: std::mem::take(&mut self.stack),
+                            env: std::mem::replace(&mut self.env, push(&env, arg)),
+                            control: std::mem::replace(
+                                &mut self.control,
+                                vec![Instruction::Term(body)],
+                            ),
+                        });
+                    }
+                    Value::Neutral(head, mut args) => {
+                        args.push(arg);
+                        self.stack.push(Value::Neutral(head, args));
+                    }
+                }
+            }
+            None => {
+                let Some(dump) = self.dump.pop() else {
+                    return false;
+                };
+                let value = self.stack.pop();
+                self.stack = dump.stack;
+                self.env = dump.env;
+                self.control = dump.control;
+                self.stack.extend(value);
+            }
+        }
+        true
+    }
+
+    /// The stack, the environment, the control and how many calls are on the dump, the top of the
+    /// stack and the next instruction are first.
+    pub fn format(&self, notation: Notation) -> String {
+        let env = to_exprs(&self.env);
+        let control = self
+            .control
+            .iter()
+            .rev()
+            .map(|instruction| match instruction {
+                Instruction::Term(expr) => expr.format_with(env.len() as u32, notation),
+                Instruction::Apply => String::from("ap"),
+            })
+            .collect::<Vec<String>>();
+
+        format!(
+            "{}\t{}\t[{}]\t{}",
+            format_list(self.stack.iter().rev().map(Value::to_expr), notation),
+            format_env(&env, notation),
+            control.join(", "),
+            self.dump.len()
+        )
+    }
+
+    /// The top of the stack, only complete when the machine has stopped.
+    pub fn result(&self) -> Expr {
+        match self.stack.last() {
+            Some(value) => value.to_expr(),
+            None => Expr::Group(Vec::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Krivine,
+    Cek,
+    Secd,
+}
+
+pub enum Machine<'a> {
+    Krivine(Krivine<'a>),
+    Cek(Cek<'a>),
+    Secd(Secd<'a>),
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(kind: Kind, expr: &'a Expr) -> Self {
+        match kind {
+            Kind::Krivine => Machine::Krivine(Krivine::new(expr)),
+            Kind::Cek => Machine::Cek(Cek::new(expr)),
+            Kind::Secd => Machine::Secd(Secd::new(expr)),
+        }
+    }
+
+    /// Returns `false` when the machine has stopped.
+    pub fn step(&mut self) -> bool {
+        match self {
+            Machine::Krivine(machine) => machine.step(),
+            Machine::Cek(machine) => machine.step(),
+            Machine::Secd(machine) => machine.step(),
+        }
+    }
+
+    pub fn format(&self, notation: Notation) -> String {
+        match self {
+            Machine::Krivine(machine) => machine.format(notation),
+            Machine::Cek(machine) => machine.format(notation),
+            Machine::Secd(machine) => machine.format(notation),
+        }
+    }
+
+    pub fn result(&self) -> Expr {
+        match self {
+            Machine::Krivine(machine) => machine.result(),
+            Machine::Cek(machine) => machine.result(),
+            Machine::Secd(machine) => machine.result(),
+        }
+    }
+}
+
+#[cfg(test)]
+fn run(kind: Kind, expr: &Expr) -> (Expr, usize) {
+    let mut machine = Machine::new(kind, expr);
+    let mut steps = 0;
+    while machine.step() {
+        steps += 1;
+    }
+    (machine.result(), steps)
+}
+
+#[test]
+fn machines() {
+    // add 1 2, sub 3 1 and not true
+    for text in [
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        "((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f x)))) (f:x:(f x)))",
+        "((b:(b x:y:y x:y:x)) x:y:x)",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        let expected = crate::nbe::normalize(&expr);
+
+        for kind in [Kind::Krivine, Kind::Cek, Kind::Secd] {
+            let (result, _) = run(kind, &expr);
+            assert!(crate::nbe::normalize(&result).same(&expected), "{kind:?}");
+        }
+    }
+
+    // the argument is not used, so call-by-name does not evaluate it
+    let expr = Expr::parse("((a:b:a) 'X ((c:c) 'Y))").unwrap();
+    assert_eq!(run(Kind::Krivine, &expr).0, Expr::Label("X".into()));
+
+    let (result, steps) = run(Kind::Cek, &expr);
+    assert_eq!(result, Expr::Label("X".into()));
+    assert!(steps > run(Kind::Krivine, &expr).1);
+
+    let expr = Expr::parse("((a:a) 'X 'Y)").unwrap();
+    let expected = Expr::parse("('X 'Y)").unwrap();
+    for kind in [Kind::Krivine, Kind::Cek, Kind::Secd] {
+        assert!(run(kind, &expr).0.same(&expected), "{kind:?}");
+    }
+}
@@ -1,4 +1,9 @@
-use lambda_man_engine::Expr;
+use std::io::{Read, Write};
+
+use lambda_man_engine::{
+    Expr, Notation, blc, church, data, defs, graph, intersection, machine, modules, nbe, optimal,
+    pcf, prim, quote, reduce, ski, subst, trace, typed, types,
+};
 
 // add 1 2
 // (a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))
@@ -6,57 +11,418 @@ use lambda_man_engine::Expr;
 // add (add 1 2) 2
 // (a:b:(a c:d:e:(d(c d e))b) (a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b)))) (a:b:(a (a b))))
 //
+// the same with an operator
+// add = a:b:(a c:d:e:(d(c d e))b)
+// infixl 6 + add
+// 1 + 2 + 2
+//
 // sub 10 6
 // ((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f(f(f(f(f(f(f(f x))))))))))) (f:x:(f(f(f(f(f(f x))))))))
 //
 // sub (fst (pair 5 1)) (snd (pair 5 1))
 // ((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m))((p:(p(x:y:x)))((x:y:f:(f x y))(f:x:(f(f(f(f(f x))))))(f:x:(f x))))((p:(p(x:y:y)))((x:y:f:(f x y))(f:x:(f(f(f(f(f x))))))(f:x:(f x)))))
+//
+// cat in BLC8, the program `a:a` is padded to a space
+// echo " hello" | lambda-man-engine --blc8
 fn main() {
+    let mode = match std::env::args().nth(1).as_deref() {
+        Some("--blc") => Some(blc::Mode::Bits),
+        Some("--blc8") => Some(blc::Mode::Bytes),
+        _ => None,
+    };
+
+    if let Some(mode) = mode {
+        let mut stream = Vec::default();
+        if std::io::stdin().read_to_end(&mut stream).is_err() {
+            std::process::exit(1);
+        }
+
+        let Some(out) = blc::run(&stream, mode) else {
+            eprintln!("Cannot run program");
+            std::process::exit(1);
+        };
+
+        _ = std::io::stdout().write_all(&out);
+        return;
+    }
+
+    // The numerals from the Church shortcuts are deep and everything on `Expr` is recursive, a
+    // numeral of `church::LIMIT` needs more than 1 GiB in a debug build
+    let repl = std::thread::Builder::new()
+        .stack_size(1 << 32)
+        .spawn(repl)
+        .expect("Cannot start the REPL");
+    _ = repl.join();
+}
+
+fn repl() {
+    let mut notation = Notation::Names;
+    let mut abstraction = None;
+    let mut system = None;
+    let mut machine = None;
+    let mut explicit = false;
+    let mut shortcuts = false;
+    let mut desugar = false;
+    let mut strategy = reduce::Strategy::default();
+    let mut definitions = defs::Definitions::prelude();
+    // The trace of the last expression
+    let mut history = None::<trace::Trace>;
+
+    // The modules are looked for here and then in the directories of `LAMBDA_MAN_PATH`
+    let mut search = vec![std::path::PathBuf::from(".")];
+    if let Some(paths) = std::env::var_os("LAMBDA_MAN_PATH") {
+        search.extend(std::env::split_paths(&paths));
+    }
+    let mut loader = modules::Loader::new(search);
+
+    let mut primitives = prim::Primitives::standard();
+    primitives.register("PRINT", 1, |args| {
+        println!("{}", args[0].format(0));
+        Some(args[0].clone())
+    });
+
     let stdin = std::io::stdin();
     loop {
         let mut line = String::default();
         print!("> ");
-        _ = std::io::Write::flush(&mut std::io::stdout());
-        if stdin.read_line(&mut line).is_err() {
+        _ = std::io::stdout().flush();
+        if !matches!(stdin.read_line(&mut line), Ok(1..)) {
             break;
         }
+        let line = line.trim();
 
-        if let Some(mut expr) = Expr::parse(line.trim()) {
-            expr.simplify();
-            println!("=={}", expr.format(0));
+        // `Y f` does not stop when the arguments are evaluated first
+        let fix = match machine {
+            Some(machine::Kind::Cek | machine::Kind::Secd) => defs::Fix::Z,
+            _ => defs::Fix::Y,
+        };
+        let parse = |line: &str| parse(line, &definitions, fix);
 
-            loop {
-                expr.simplify();
-                let betas = expr.find_beta_reductions();
+        if let Some(command) = line.strip_prefix(':') {
+            let (command, arg) = command.split_once(' ').unwrap_or((command, ""));
+            match (command, arg.trim()) {
+                ("notation", "names") => notation = Notation::Names,
+                ("notation", "debruijn") => notation = Notation::DeBruijn,
+                ("notation", "both") => notation = Notation::Both,
+                ("ski", "off") => abstraction = None,
+                ("ski", "naive") => abstraction = Some(ski::Abstraction::Naive),
+                ("ski", "turner") => abstraction = Some(ski::Abstraction::Turner),
+                ("ski", "bckw") => abstraction = Some(ski::Abstraction::Bckw),
+                ("normalizes", expr) => {
+                    let Some(expr) = parse(expr) else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+                    match intersection::certify(&expr, intersection::Bounds::default()) {
+                        Some(certificate) => {
+                            match certificate.normalization {
+                                intersection::Normalization::Strong => {
+                                    println!("Strongly normalizing")
+                                }
+                                intersection::Normalization::Weak => {
+                                    println!("Weakly normalizing")
+                                }
+                            }
+                            print!("{}", certificate.format());
+                        }
+                        None => println!("No derivation was found within the bounds"),
+                    }
+                }
+                ("nf", expr) => match parse(expr) {
+                    Some(expr) => println!("={}", nbe::normalize(&expr).format_with(0, notation)),
+                    None => eprintln!("Cannot parse"),
+                },
+                ("quote", expr) => {
+                    let (Some(expr), Some(eval)) = (parse(expr), definitions.value("eval", fix))
+                    else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+                    let quoted = quote::quote(&expr);
+                    println!("{}", quoted.format_with(0, notation));
 
-                for (score, at) in betas.iter() {
-                    println!("\t{score}: {}", expr.format_highlightd(0, at, "31"))
+                    let evaluated = nbe::normalize(&Expr::Group(vec![eval, quoted]));
+                    println!("={}", evaluated.format_with(0, notation));
+                    match evaluated.same(&nbe::normalize(&expr)) {
+                        true => println!("(eval quote) has the normal form of the term"),
+                        false => println!("(eval quote) does not have the normal form of the term"),
+                    }
+                }
+                ("need", expr) => {
+                    let Some(expr) = parse(expr) else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+                    let (normal, sharing) = graph::compare(&expr);
+                    println!("={}", normal.format_with(0, notation));
+                    println!(
+                        "{} reductions with sharing, {} without, saved {}",
+                        sharing.shared,
+                        sharing.tree,
+                        sharing.saved()
+                    );
+                }
+                ("optimal", expr) => {
+                    let Some(expr) = parse(expr) else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+                    if let Some((normal, interactions, betas)) = optimal::normalize(&expr) {
+                        println!("={}", normal.format_with(0, notation));
+                        println!("{interactions} interactions, {betas} beta reductions");
+                    }
                 }
+                ("pcf", text) => {
+                    let Some(term) = pcf::parse(text) else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+
+                    let mut native = term.clone();
+                    let mut steps = 0;
+                    println!("=={}", native.format());
+                    while let Some(rule) = native.step() {
+                        steps += 1;
+                        println!("{}\t={}", rule.name(), native.format());
+                    }
+                    println!("={}", native.format());
+                    println!("{steps} steps");
 
-                if betas.is_empty() {
-                    break;
+                    let Some(expr) = term.to_expr() else {
+                        println!("No Church encoding");
+                        continue;
+                    };
+                    let (normal, betas) = graph::normalize(&expr);
+                    match native.decode(&normal) {
+                        Some(decoded) => println!("={}", decoded.format()),
+                        None => println!("={}", normal.format_with(0, notation)),
+                    }
+                    println!("{betas} beta reductions with Church encodings");
                 }
+                ("machine", "off") => machine = None,
+                ("machine", "krivine") => machine = Some(machine::Kind::Krivine),
+                ("machine", "cek") => machine = Some(machine::Kind::Cek),
+                ("machine", "secd") => machine = Some(machine::Kind::Secd),
+                ("subst", "off") => explicit = false,
+                ("subst", "on") => explicit = true,
+                ("church", "off") => shortcuts = false,
+                ("church", "on") => shortcuts = true,
+                ("strategy", "innermost") => strategy = reduce::Strategy::Innermost,
+                ("strategy", "normal") => strategy = reduce::Strategy::Normal,
+                ("desugar", "hide") => desugar = false,
+                ("desugar", "show") => desugar = true,
+                ("data", "scott") => definitions.encoding = data::Encoding::Scott,
+                ("data", "church") => definitions.encoding = data::Encoding::Church,
+                ("history", "") => {
+                    let Some(trace) = &history else {
+                        eprintln!("No expression was reduced");
+                        continue;
+                    };
+                    for (i, step) in trace.steps().iter().enumerate() {
+                        println!("{}\t{}", i + 1, step.action.format());
+                    }
+                }
+                ("seek", n) => {
+                    let term = match (&mut history, n.parse()) {
+                        (Some(trace), Ok(n)) => trace.seek(n),
+                        _ => None,
+                    };
+                    match term {
+                        Some(term) => println!("={}", term.format_with(0, notation)),
+                        None => eprintln!("No such step"),
+                    }
+                }
+                ("compare", expr) => {
+                    let (Some(trace), Some(expr)) = (&history, parse(expr)) else {
+                        eprintln!("Cannot parse");
+                        continue;
+                    };
+                    let mut other = trace::Trace::new(expr);
+                    other.strategy = definitions.strategy(other.current(), fix, strategy);
+                    while other.step(Some(&primitives), shortcuts).is_some() {}
 
-                let mut max_i = 0;
-                let mut last_score = 0;
-                for (i, (score, _)) in betas.iter().enumerate() {
-                    if *score > last_score {
-                        last_score = *score;
-                        max_i = i;
+                    let comparison = trace.compare(&other);
+                    println!(
+                        "{} common steps of {} and {}",
+                        comparison.common, comparison.lengths.0, comparison.lengths.1
+                    );
+                    match comparison.same_result {
+                        true => println!("The same result"),
+                        false => println!("Different results"),
                     }
                 }
+                ("primitives", "") => {
+                    for (name, arity) in primitives.list() {
+                        println!("'{name}\t{arity}");
+                    }
+                }
+                ("typed", "off") => system = None,
+                ("typed", "stlc") => system = Some(typed::System::Stlc),
+                ("typed", "f") => system = Some(typed::System::SystemF),
+                ("typed", "coc") => system = Some(typed::System::Coc),
+                ("type", expr) => match parse(expr).map(|expr| types::infer(&expr)) {
+                    Some(Ok(ty)) => println!("{}", ty.format()),
+                    Some(Err(error)) => eprintln!("{error}"),
+                    None => eprintln!("Cannot parse"),
+                },
+                _ => {
+                    eprintln!("Commands:");
+                    eprintln!("\t:notation names|debruijn|both");
+                    eprintln!("\t:ski off|naive|turner|bckw");
+                    eprintln!("\t:type EXPR");
+                    eprintln!("\t:normalizes EXPR");
+                    eprintln!("\t:nf EXPR");
+                    eprintln!("\t:need EXPR");
+                    eprintln!("\t:quote EXPR");
+                    eprintln!("\t:optimal EXPR");
+                    eprintln!("\t:pcf EXPR");
+                    eprintln!("\t:typed off|stlc|f|coc");
+                    eprintln!("\t:machine off|krivine|cek|secd");
+                    eprintln!("\t:subst off|on");
+                    eprintln!("\t:church off|on");
+                    eprintln!("\t:strategy innermost|normal");
+                    eprintln!("\t:primitives");
+                    eprintln!("\t:history");
+                    eprintln!("\t:seek N");
+                    eprintln!("\t:compare EXPR");
+                    eprintln!("\t:desugar hide|show");
+                    eprintln!("\t:data scott|church");
+                }
+            }
+            continue;
+        }
+
+        if modules::Loader::is_import(line) {
+            if let Some(names) = loader.import(line, &mut definitions, None) {
+                println!("{}", names.join(" "));
+            }
+            continue;
+        }
+
+        if defs::Definitions::is_statement(line) {
+            let Some(names) = definitions.define(line) else {
+                eprintln!("Cannot parse");
+                continue;
+            };
+            for name in names {
+                match definitions.value(&name, fix).filter(|_| desugar) {
+                    Some(value) => println!("{name} = {}", value.format_with(0, notation)),
+                    None => println!("{name}"),
+                }
+            }
+            continue;
+        }
 
-                println!(" {}", expr.format_highlightd(0, &betas[max_i].1, "31"));
+        let expr = match system {
+            Some(system) => parse_typed(line, system),
+            None => parse(line),
+        };
 
-                expr.beta_reduction_at(&betas[max_i].1);
+        // The recursive definitions are shown as their names
+        let shown = |expr: &Expr| match desugar {
+            true => expr.clone(),
+            false => definitions.fold(expr, fix),
+        };
 
-                println!("={}", expr.format_highlightd(0, &betas[max_i].1, "32"));
+        if let Some(mut expr) = expr {
+            expr.simplify();
+            println!("=={}", shown(&expr).format_with(0, notation));
+
+            let term = abstraction.and_then(|abstraction| ski::Term::from_expr(&expr, abstraction));
+            let start = machine.map(|_| expr.clone());
+            let explicit_term = explicit.then(|| subst::Term::from_expr(&expr));
+
+            // `Y f` is reduced forever when its innermost redex is chosen
+            let mut trace = trace::Trace::new(expr);
+            trace.strategy = definitions.strategy(trace.current(), fix, strategy);
+            while let Some(step) = trace.step(Some(&primitives), shortcuts) {
+                let trace::Action::Beta { candidates, chosen } = &step.action else {
+                    println!(" {}", step.action.format());
+                    continue;
+                };
+
+                // The paths are in the terms before folding, a path into a folded definition
+                // ends at its name
+                let before = shown(&step.before);
+                for (score, at) in candidates.iter() {
+                    let at = before.valid_path(at);
+                    println!(
+                        "\t{score}: {}",
+                        before.format_highlightd_with(0, at, "31", notation)
+                    )
+                }
+
+                let at = &candidates[*chosen].1;
+                println!(
+                    " {}",
+                    before.format_highlightd_with(0, before.valid_path(at), "31", notation)
+                );
+                let after = shown(&step.after);
+                println!(
+                    "={}",
+                    after.format_highlightd_with(0, after.valid_path(at), "32", notation)
+                );
             }
 
+            let mut expr = trace.last().clone();
+            history = Some(trace);
             expr.simplify();
-            println!("={}", expr.format(0));
+            if let Some(num) = church::numeral(&expr).filter(|_| shortcuts) {
+                println!("={num}");
+            } else if let Some(value) = definitions.decode(&expr) {
+                println!("={value}");
+            } else {
+                println!("={}", shown(&expr).format_with(0, notation));
+            }
+
+            if let Some(mut term) = term {
+                println!("=={}", term.format());
+                while let Some(comb) = term.step() {
+                    println!("{}\t={}", comb.name(), term.format());
+                }
+                println!("={}", term.to_expr().format_with(0, notation));
+            }
+
+            if let Some(mut term) = explicit_term {
+                println!("=={}", term.format());
+                while let Some(rule) = term.step() {
+                    println!("{}\t={}", rule.name(), term.format());
+                }
+                if let Some(expr) = term.to_expr() {
+                    println!("={}", expr.format_with(0, notation));
+                }
+            }
+
+            if let (Some(kind), Some(start)) = (machine, &start) {
+                let mut machine = machine::Machine::new(kind, start);
+                println!("=={}", machine.format(notation));
+                while machine.step() {
+                    println!("\t{}", machine.format(notation));
+                }
+                println!("={}", machine.result().format_with(0, notation));
+            }
         } else {
             eprintln!("Cannot parse");
         }
     }
 }
+
+fn parse(line: &str, definitions: &defs::Definitions, fix: defs::Fix) -> Option<Expr> {
+    if line.contains(['λ', '\\']) {
+        Expr::parse_de_bruijn(line)
+    } else {
+        definitions.parse(line, fix)
+    }
+}
+
+fn parse_typed(line: &str, system: typed::System) -> Option<Expr> {
+    let term = typed::parse(line)?;
+    match typed::check(&term, system) {
+        Ok(ty) => println!("::{}", ty.format()),
+        Err(error) => {
+            eprintln!("{error}");
+            return None;
+        }
+    }
+    typed::erase(&term)
+}
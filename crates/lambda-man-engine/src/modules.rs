@@ -0,0 +1,281 @@
+// Modules
+//
+// A module is a `.lam` file with a definition on each line, the lines that start with `//` are
+// comments.
+//
+// import arith          the exported names as `add` and as `arith.add`
+// import lists as L     the exported names as `L.map` only
+// export map fold       the names that can be imported, all the names of the module without it
+//
+// `import data.lists` is the file `data/lists.lam`, the parts of a name are letters and digits. It
+// is looked for next to the file that imports it and then in the directories of the search path. A
+// module is loaded once and the modules that import it share its definitions, an import that leads
+// back to a module that is still loading is a cycle.
+//
+// The imported names are definitions like the others, they are replaced with closed expressions
+// when an expression is parsed.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::defs::Definitions;
+
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub definitions: Rc<Definitions>,
+    pub exports: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Loader {
+    pub search: Vec<PathBuf>,
+    modules: HashMap<PathBuf, Rc<Module>>,
+    /// The modules that are loading, the last one imports the next.
+    loading: Vec<(String, PathBuf)>,
+}
+
+impl Loader {
+    pub fn new(search: Vec<PathBuf>) -> Loader {
+        Loader {
+            search,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_import(line: &str) -> bool {
+        line.trim_start().starts_with("import ")
+    }
+
+    /// Loads the module of the line and adds its names to the definitions, the module is looked
+    /// for next to `from` first.
+    pub fn import(
+        &mut self,
+        line: &str,
+        definitions: &mut Definitions,
+        from: Option<&Path>,
+    ) -> Option<Vec<String>> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (name, alias) = match words.as_slice() {
+            ["import", name] => (*name, None),
+            ["import", name, "as", alias] => (*name, Some(*alias)),
+            _ => {
+                eprintln!("Expected `import NAME` or `import NAME as ALIAS`");
+                return None;
+            }
+        };
+
+        let module = self.load(name, from)?;
+        Some(definitions.import(
+            &module.definitions,
+            &module.exports,
+            alias.unwrap_or(name),
+            alias.is_none(),
+        ))
+    }
+
+    pub fn load(&mut self, name: &str, from: Option<&Path>) -> Option<Rc<Module>> {
+        // not a path, `.x` or `/x` would be looked for outside of the directories
+        if name
+            .split('.')
+            .any(|part| part.is_empty() || !part.chars().all(char::is_alphanumeric))
+        {
+            eprintln!("`{name}` is not the name of a module");
+            return None;
+        }
+        let Some(path) = self.find(name, from) else {
+            eprintln!("Cannot find module `{name}`");
+            return None;
+        };
+        if let Some(module) = self.modules.get(&path) {
+            return Some(module.clone());
+        }
+
+        if let Some(start) = self
+            .loading
+            .iter()
+            .position(|(_, loading)| *loading == path)
+        {
+            let cycle = self.loading[start..]
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .chain([name])
+                .collect::<Vec<_>>();
+            eprintln!("Import cycle {}", cycle.join(" -> "));
+            return None;
+        }
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("Cannot read {}: {error}", path.display());
+                return None;
+            }
+        };
+
+        self.loading.push((name.to_string(), path.clone()));
+        let module = self.parse(name, &path, &text);
+        self.loading.pop();
+
+        let module = Rc::new(module?);
+        self.modules.insert(path, module.clone());
+        Some(module)
+    }
+
+    fn parse(&mut self, name: &str, path: &Path, text: &str) -> Option<Module> {
+        let mut definitions = Definitions::default();
+        let mut exports = None::<Vec<String>>;
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let done = if Self::is_import(line) {
+                self.import(line, &mut definitions, Some(path)).is_some()
+            } else if let Some(names) = line.strip_prefix("export ") {
+                let exports = exports.get_or_insert_with(Vec::default);
+                exports.extend(names.split_whitespace().map(String::from));
+                true
+            } else if Definitions::is_statement(line) {
+                definitions.define(line).is_some()
+            } else {
+                eprintln!("Not a definition");
+                false
+            };
+
+            if !done {
+                eprintln!("{}:{}: {line}", path.display(), n + 1);
+                return None;
+            }
+        }
+
+        let names = definitions.names();
+        let exports = match exports {
+            Some(exports) => {
+                if let Some(missing) = exports.iter().find(|export| !names.contains(export)) {
+                    eprintln!(
+                        "{}: `{missing}` is exported but not defined",
+                        path.display()
+                    );
+                    return None;
+                }
+                exports
+            }
+            None => names,
+        };
+
+        Some(Module {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            definitions: Rc::new(definitions),
+            exports,
+        })
+    }
+
+    fn find(&self, name: &str, from: Option<&Path>) -> Option<PathBuf> {
+        let file = PathBuf::from(format!("{}.lam", name.replace('.', "/")));
+        let next_to = from.and_then(Path::parent);
+        next_to
+            .into_iter()
+            .chain(self.search.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+    }
+}
+
+#[cfg(test)]
+fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lambda-man-{name}-{}", std::process::id()));
+    for (file, text) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn imports() {
+    let dir = directory(
+        "imports",
+        &[
+            (
+                "arith.lam",
+                "// Church arithmetic\n\
+                 export add\n\
+                 succ = n:f:x:(f (n f x))\n\
+                 add = a:b:(a succ b)\n\
+                 infixl 6 + add\n",
+            ),
+            (
+                "data/lists.lam",
+                "import arith\n\
+                 data List = Nil | Cons head tail:List\n\
+                 rec sum = xs:(case xs of Nil -> 0 | Cons h t -> h + sum t)\n\
+                 export Nil Cons sum\n",
+            ),
+        ],
+    );
+
+    let mut loader = Loader::new(vec![dir.clone()]);
+    let mut definitions = Definitions::default();
+    let names = loader
+        .import("import data.lists as L", &mut definitions, None)
+        .unwrap();
+    assert_eq!(names, ["L.Nil", "L.Cons", "L.sum"]);
+    loader
+        .import("import arith", &mut definitions, None)
+        .unwrap();
+
+    let text = "(L.sum (L.Cons 1 (L.Cons 2 L.Nil))) + 3";
+    let expr = definitions.parse(text, crate::defs::Fix::Y).unwrap();
+    let normal = crate::nbe::normalize(&expr);
+    assert_eq!(crate::church::numeral(&normal), Some(6));
+
+    let expr = definitions.parse("(L.Cons 1 L.Nil)", crate::defs::Fix::Y);
+    let normal = crate::nbe::normalize(&expr.unwrap());
+    assert_eq!(definitions.decode(&normal).unwrap(), "L.Cons 1 L.Nil");
+
+    // not exported and not imported as unqualified
+    assert!(definitions.parse("succ", crate::defs::Fix::Y).is_none());
+    assert!(definitions.parse("sum", crate::defs::Fix::Y).is_none());
+    _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn cycles() {
+    let dir = directory(
+        "cycles",
+        &[
+            ("a.lam", "import b\nx = a:a\n"),
+            ("b.lam", "import c\n"),
+            ("c.lam", "import a\n"),
+            ("d.lam", "export y\nx = a:a\n"),
+        ],
+    );
+
+    let mut loader = Loader::new(vec![dir.clone()]);
+    assert!(loader.load("a", None).is_none());
+    assert!(loader.load("d", None).is_none());
+    assert!(loader.load("e", None).is_none());
+    _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn names() {
+    let dir = directory("names", &[("x.lam", "x = a:a\n")]);
+
+    let mut loader = Loader::new(vec![dir.clone()]);
+    let absolute = dir.join("x").display().to_string();
+    for name in [".x", "x.", "..x", "/x", "x/", absolute.as_str()] {
+        assert!(loader.load(name, None).is_none());
+    }
+    let mut definitions = Definitions::default();
+    assert!(loader.import("import .x", &mut definitions, None).is_none());
+    assert!(loader.import("import x", &mut definitions, None).is_some());
+    _ = std::fs::remove_dir_all(dir);
+}
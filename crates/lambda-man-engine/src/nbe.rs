@@ -0,0 +1,162 @@
+// Normalization by evaluation
+//
+// The expression is evaluated to a value where a function is a closure, a variable that is not
+// known is neutral and applying a neutral gives a neutral. The value is read back to an `Expr`,
+// under every function the closure is applied to a new neutral variable.
+//
+// The arguments are evaluated only when used and only once, so an argument that has no normal form
+// and is never used does not stop the evaluation.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::Expr;
+
+#[derive(Clone)]
+enum Value<'a> {
+    Lam(Env<'a>, &'a Expr),
+    Neutral(Head, Vec<Thunk<'a>>),
+}
+
+#[derive(Clone)]
+enum Head {
+    /// The variable of the function at this depth.
+    Var(u32),
+    Label(String),
+    /// `()`
+    Empty,
+}
+
+enum Lazy<'a> {
+    Delayed(Env<'a>, &'a Expr),
+    Done(Value<'a>),
+}
+
+type Thunk<'a> = Rc<RefCell<Lazy<'a>>>;
+
+struct Node<'a> {
+    value: Thunk<'a>,
+    next: Env<'a>,
+}
+
+type Env<'a> = Option<Rc<Node<'a>>>;
+
+fn push<'a>(env: &Env<'a>, value: Thunk<'a>) -> Env<'a> {
+    Some(Rc::new(Node {
+        value,
+        next: env.clone(),
+    }))
+}
+
+fn force<'a>(thunk: &Thunk<'a>) -> Value<'a> {
+    let (env, expr) = match &*thunk.borrow() {
+        Lazy::Done(value) => return value.clone(),
+        Lazy::Delayed(env, expr) => (env.clone(), *expr),
+    };
+
+    let value = eval(&env, expr);
+    *thunk.borrow_mut() = Lazy::Done(value.clone());
+    value
+}
+
+fn eval<'a>(env: &Env<'a>, expr: &'a Expr) -> Value<'a> {
+    match expr {
+        Expr::Group(exprs) => {
+            let Some((first, rest)) = exprs.split_first() else {
+                return Value::Neutral(Head::Empty, Vec::default());
+            };
+
+            let mut value = eval(env, first);
+            for expr in rest {
+                let argument = Rc::new(RefCell::new(Lazy::Delayed(env.clone(), expr)));
+                value = apply(value, argument);
+            }
+            value
+        }
+        Expr::Def(body) => Value::Lam(env.clone(), body),
+        Expr::Relative(id) => {
+            let mut node = env.as_ref();
+            for _ in 0..*id {
+                node = node.and_then(|node| node.next.as_ref());
+            }
+            match node {
+                Some(node) => force(&node.value),
+                None => panic!("Variable {id} is not bound"),
+            }
+        }
+        Expr::Label(name) => Value::Neutral(Head::Label(name.clone()), Vec::default()),
+    }
+}
+
+fn apply<'a>(function: Value<'a>, argument: Thunk<'a>) -> Value<'a> {
+    match function {
+        Value::Lam(env, body) => eval(&push(&env, argument), body),
+        Value::Neutral(head, mut args) => {
+            args.push(argument);
+            Value::Neutral(head, args)
+        }
+    }
+}
+
+fn read_back(value: Value, depth: u32) -> Expr {
+    match value {
+        Value::Lam(env, body) => {
+            let var = Value::Neutral(Head::Var(depth), Vec::default());
+            let body = eval(&push(&env, Rc::new(RefCell::new(Lazy::Done(var)))), body);
+            Expr::Def(Box::new(read_back(body, depth + 1)))
+        }
+        Value::Neutral(head, args) => {
+            let head = match head {
+                Head::Var(level) => Expr::Relative(depth - level - 1),
+                Head::Label(name) => Expr::Label(name),
+                Head::Empty => Expr::Group(Vec::default()),
+            };
+
+            if args.is_empty() {
+                return head;
+            }
+
+            let mut exprs = vec![head];
+            for arg in args {
+                exprs.push(read_back(force(&arg), depth));
+            }
+            Expr::Group(exprs)
+        }
+    }
+}
+
+/// The beta normal form, does not return if the expression has none.
+///
+/// The expression needs to be closed, only labels can be free.
+pub fn normalize(expr: &Expr) -> Expr {
+    read_back(eval(&None, expr), 0)
+}
+
+#[test]
+fn same_as_stepping() {
+    for text in [
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        // add (add 1 2) 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b)))) (a:b:(a (a b))))",
+        // sub 10 6
+        "((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f(f(f(f(f(f(f(f x))))))))))) (f:x:(f(f(f(f(f(f x))))))))",
+        "((a:b:a) 'TRUE 'FALSE)",
+        "(x:((y:y) x))",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        let nbe = normalize(&expr);
+        let engine = crate::reduce::normalize(&expr, crate::reduce::Strategy::Innermost);
+        assert!(nbe.same(&engine));
+    }
+}
+
+#[test]
+fn lazy_arguments() {
+    // (a:b:b) Ω
+    let expr = Expr::parse("((a:b:b) ((a:(a a)) (a:(a a))))").unwrap();
+    assert_eq!(normalize(&expr), Expr::parse("a:a").unwrap());
+
+    // exp 2 10 = 1024
+    let expr = Expr::Group(vec![Expr::from(10), Expr::from(2)]);
+    assert!(normalize(&expr).same(&Expr::from(1024)));
+}
@@ -0,0 +1,485 @@
+// Optimal reduction with interaction nets, [Lambdascope](https://doi.org/10.1007/978-3-540-30477-7_5)
+//
+// Every node has a principal port and auxiliary ports, two nodes interact only when their principal
+// ports are connected. A function has the parent as principal, the body and the variable. An
+// application has the function as principal, the argument and the parent.
+//
+// A variable that is used more than once is shared with fans. A function and the scope nodes on the
+// variables that come from outside of its body are the border of its scope, after a beta reduction
+// scope nodes take the place of the function. A fan or a scope node that goes into a scope gets one
+// level higher and only nodes of the same kind and level annihilate, so the fans of different copies
+// of a function are kept apart.
+//
+// The net is reduced by reading it back from the root, the first pair on the path that can interact
+// is reduced. A fan that is entered from a side is left to the same side, the path remembers the
+// sides for every level.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Root,
+    Lam,
+    App,
+    Fan(u32),
+    Scope(u32),
+    Eraser,
+    /// A label or `()`
+    Const(Expr),
+}
+
+impl Kind {
+    fn aux(&self) -> usize {
+        match self {
+            Kind::Lam | Kind::App | Kind::Fan(_) => 2,
+            Kind::Scope(_) => 1,
+            Kind::Root | Kind::Eraser | Kind::Const(_) => 0,
+        }
+    }
+
+    fn level(&self) -> Option<u32> {
+        match self {
+            Kind::Fan(level) | Kind::Scope(level) => Some(*level),
+            _ => None,
+        }
+    }
+
+    fn with_level(&self, level: Option<u32>) -> Kind {
+        match (self, level) {
+            (Kind::Fan(_), Some(level)) => Kind::Fan(level),
+            (Kind::Scope(_), Some(level)) => Kind::Scope(level),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// The copy of `kind` that passed through `other`, going into a scope makes the level higher.
+fn passed(kind: &Kind, other: &Kind) -> Kind {
+    let level = match (kind.level(), other) {
+        (Some(level), Kind::Lam) => Some(level + 1),
+        (Some(level), Kind::Scope(scope)) if level >= *scope => Some(level + 1),
+        (level, _) => level,
+    };
+    kind.with_level(level)
+}
+
+fn port(node: usize, slot: usize) -> usize {
+    node * 3 + slot
+}
+
+fn node(port: usize) -> usize {
+    port / 3
+}
+
+fn slot(port: usize) -> usize {
+    port % 3
+}
+
+/// What a path remembers on a level, the side of a fan that it entered or a level of a scope that
+/// it left.
+#[derive(Debug, Clone, PartialEq)]
+enum Mark {
+    Side(usize),
+    Level(Vec<Mark>),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Context(Vec<Vec<Mark>>);
+
+impl Context {
+    fn level(&mut self, level: u32) -> &mut Vec<Mark> {
+        let level = level as usize;
+        if self.0.len() <= level {
+            self.0.resize(level + 1, Vec::default());
+        }
+        &mut self.0[level]
+    }
+
+    /// Going into a scope the levels from `level` are one higher, the level that was left last is
+    /// back.
+    fn enter(&mut self, level: u32) {
+        let marks = match self.level(level).last() {
+            Some(Mark::Level(_)) => match self.level(level).pop() {
+                Some(Mark::Level(marks)) => marks,
+                _ => unreachable!(),
+            },
+            _ => Vec::default(),
+        };
+        self.0.insert(level as usize, marks);
+    }
+
+    /// The context of a variable is the context of its function, only the level of the function
+    /// can be different.
+    fn binds(&self, var: &Context) -> bool {
+        let used = |context: &Context| {
+            let len = context.0.iter().rposition(|marks| !marks.is_empty());
+            len.map_or(1, |i| i + 1).max(1)
+        };
+        self.0.get(1..used(self)).unwrap_or_default() == var.0.get(1..used(var)).unwrap_or_default()
+    }
+
+    /// Going out of a scope the levels after `level` are one lower, `level` is kept in the next.
+    fn leave(&mut self, level: u32) {
+        self.level(level + 1);
+        let marks = self.0.remove(level as usize);
+        self.level(level).push(Mark::Level(marks));
+    }
+}
+
+enum Stop {
+    /// The path needs to go back to the last node.
+    Again,
+    Broken,
+}
+
+#[derive(Debug, Default)]
+pub struct Net {
+    kinds: Vec<Kind>,
+    wires: Vec<usize>,
+    /// How many interactions were made.
+    pub interactions: usize,
+    /// How many of the interactions were beta reductions.
+    pub betas: usize,
+}
+
+impl Net {
+    fn add(&mut self, kind: Kind) -> usize {
+        let node = self.kinds.len();
+        self.kinds.push(kind);
+        self.wires
+            .extend([port(node, 0), port(node, 1), port(node, 2)]);
+        node
+    }
+
+    fn link(&mut self, a: usize, b: usize) {
+        self.wires[a] = b;
+        self.wires[b] = a;
+    }
+
+    /// Compiles a closed expression, the root is the node `0`.
+    pub fn new(expr: &Expr) -> Option<Self> {
+        let mut net = Net::default();
+        let root = net.add(Kind::Root);
+        let mut free = Vec::default();
+        net.compile(expr, 0, port(root, 0), &mut free);
+        if free.is_empty() {
+            Some(net)
+        } else {
+            eprintln!("Only closed terms can be compiled");
+            None
+        }
+    }
+
+    /// Connects `expr` to `up`, the ports that need a variable are added to `free` with the depth
+    /// of the function.
+    fn compile(&mut self, expr: &Expr, depth: u32, up: usize, free: &mut Vec<(u32, usize)>) {
+        match expr {
+            Expr::Group(exprs) if exprs.len() > 1 => {
+                let (last, rest) = exprs.split_last().unwrap();
+                let app = self.add(Kind::App);
+                self.link(port(app, 2), up);
+                self.compile(&Expr::Group(rest.to_vec()), depth, port(app, 0), free);
+                self.compile(last, depth, port(app, 1), free);
+            }
+            Expr::Group(exprs) if exprs.len() == 1 => self.compile(&exprs[0], depth, up, free),
+            Expr::Group(_) | Expr::Label(_) => {
+                let node = self.add(Kind::Const(expr.clone()));
+                self.link(port(node, 0), up);
+            }
+            Expr::Def(body) => {
+                let lam = self.add(Kind::Lam);
+                self.link(port(lam, 0), up);
+
+                let mut inner = Vec::default();
+                self.compile(body, depth + 1, port(lam, 1), &mut inner);
+
+                let (used, mut outer): (Vec<_>, Vec<_>) =
+                    inner.into_iter().partition(|(level, _)| *level == depth);
+                self.share(used.iter().map(|(_, port)| *port).collect(), port(lam, 2));
+
+                while let Some((level, _)) = outer.first().copied() {
+                    let (ports, rest): (Vec<_>, Vec<_>) =
+                        outer.into_iter().partition(|(l, _)| *l == level);
+                    outer = rest;
+
+                    let scope = self.add(Kind::Scope(0));
+                    self.share(
+                        ports.iter().map(|(_, port)| *port).collect(),
+                        port(scope, 1),
+                    );
+                    free.push((level, port(scope, 0)));
+                }
+            }
+            Expr::Relative(id) => free.push((depth - id - 1, up)),
+        }
+    }
+
+    /// Connects all the `ports` to `to` with fans.
+    fn share(&mut self, ports: Vec<usize>, to: usize) {
+        match ports.len() {
+            0 => {
+                let eraser = self.add(Kind::Eraser);
+                self.link(port(eraser, 0), to);
+            }
+            1 => self.link(ports[0], to),
+            _ => {
+                let fan = self.add(Kind::Fan(0));
+                self.link(port(fan, 0), to);
+                self.link(ports[0], port(fan, 1));
+                self.share(ports[1..].to_vec(), port(fan, 2));
+            }
+        }
+    }
+
+    fn active(&self, a: usize, b: usize) -> bool {
+        !matches!(
+            (&self.kinds[a], &self.kinds[b]),
+            (Kind::Root, _)
+                | (_, Kind::Root)
+                | (Kind::App, Kind::Const(_))
+                | (Kind::Const(_), Kind::App)
+        )
+    }
+
+    /// Moves the connections of the old ports to the new ports, two old ports that are connected
+    /// to each other connect the new ports.
+    fn rewire(&mut self, ports: &[(usize, usize)]) {
+        let neighbors = ports
+            .iter()
+            .map(|(old, _)| {
+                let neighbor = self.wires[*old];
+                ports
+                    .iter()
+                    .find(|(old, _)| *old == neighbor)
+                    .map_or(neighbor, |(_, new)| *new)
+            })
+            .collect::<Vec<usize>>();
+        for ((_, new), neighbor) in ports.iter().zip(neighbors) {
+            self.link(*new, neighbor);
+        }
+    }
+
+    fn interact(&mut self, a: usize, b: usize) {
+        self.interactions += 1;
+
+        let (ka, kb) = (self.kinds[a].clone(), self.kinds[b].clone());
+        match (&ka, &kb) {
+            (Kind::Lam, Kind::App) | (Kind::App, Kind::Lam) => {
+                self.betas += 1;
+                let (lam, app) = if ka == Kind::Lam { (a, b) } else { (b, a) };
+                // the body and the argument stay in the scope of the function
+                let body = self.add(Kind::Scope(0));
+                let var = self.add(Kind::Scope(0));
+                self.rewire(&[
+                    (port(app, 2), port(body, 0)),
+                    (port(lam, 1), port(body, 1)),
+                    (port(app, 1), port(var, 0)),
+                    (port(lam, 2), port(var, 1)),
+                ]);
+            }
+            (Kind::Eraser, _) | (_, Kind::Eraser) => {
+                let other = if ka == Kind::Eraser { b } else { a };
+                let ports = (1..=self.kinds[other].aux())
+                    .map(|slot| (port(other, slot), port(self.add(Kind::Eraser), 0)))
+                    .collect::<Vec<_>>();
+                self.rewire(&ports);
+            }
+            _ if ka == kb => {
+                for slot in 1..=ka.aux() {
+                    // a connection made in the last slot can change this one
+                    let (x, y) = (self.wires[port(a, slot)], self.wires[port(b, slot)]);
+                    self.link(x, y);
+                }
+            }
+            _ => {
+                let copies_a = (0..kb.aux())
+                    .map(|_| self.add(passed(&ka, &kb)))
+                    .collect::<Vec<usize>>();
+                let copies_b = (0..ka.aux())
+                    .map(|_| self.add(passed(&kb, &ka)))
+                    .collect::<Vec<usize>>();
+
+                let mut ports = Vec::default();
+                for (slot, copy) in copies_b.iter().enumerate() {
+                    ports.push((port(a, slot + 1), port(*copy, 0)));
+                }
+                for (slot, copy) in copies_a.iter().enumerate() {
+                    ports.push((port(b, slot + 1), port(*copy, 0)));
+                }
+                self.rewire(&ports);
+
+                for (k, copy_a) in copies_a.iter().enumerate() {
+                    for (l, copy_b) in copies_b.iter().enumerate() {
+                        self.link(port(*copy_a, l + 1), port(*copy_b, k + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The path from `from` reaches a pair that interacts.
+    fn is_active(&self, from: usize) -> bool {
+        let to = self.wires[from];
+        slot(from) == 0 && slot(to) == 0 && self.active(node(from), node(to))
+    }
+
+    /// Where the path goes back to when it goes on from `next` and the next pair interacts, the
+    /// context is only copied then.
+    fn back(&self, next: usize, from: usize, context: &Context) -> Option<(usize, Context)> {
+        self.is_active(next).then(|| (from, context.clone()))
+    }
+
+    /// Follows the paths from `from` and reduces the pairs that are on them, returns
+    /// `Err(Stop::Again)` when the node of `from` was reduced.
+    fn read_back(
+        &mut self,
+        start: usize,
+        mut context: Context,
+        lams: &mut Vec<(usize, Context)>,
+    ) -> Result<Expr, Stop> {
+        let mut from = start;
+        // where the path was before the last node, to go back when the node is reduced
+        let mut back: Option<(usize, Context)> = None;
+
+        loop {
+            let to = self.wires[from];
+            let (node, slot) = (node(to), slot(to));
+
+            if self.is_active(from) {
+                self.interact(self::node(from), node);
+                (from, context) = back.take().ok_or(Stop::Again)?;
+                continue;
+            }
+
+            match (&self.kinds[node], slot) {
+                (Kind::Lam, 0) => {
+                    let mut inner = context.clone();
+                    inner.enter(0);
+                    lams.push((node, inner.clone()));
+                    let body = self.read_back(port(node, 1), inner, lams);
+                    lams.pop();
+                    return Ok(Expr::Def(Box::new(body?)));
+                }
+                (Kind::Lam, 2) => {
+                    // the same function can be on the path more than once, the variable is of the
+                    // one that was entered from the same context
+                    let i = lams
+                        .iter()
+                        .rev()
+                        .position(|(lam, entered)| *lam == node && entered.binds(&context))
+                        .ok_or(Stop::Broken)?;
+                    return Ok(Expr::Relative(i as u32));
+                }
+                (Kind::App, 2) => {
+                    let function = match self.read_back(port(node, 0), context.clone(), lams) {
+                        Err(Stop::Again) => continue,
+                        function => function?,
+                    };
+                    let arg = self.read_back(port(node, 1), context, lams)?;
+                    let mut exprs = match function {
+                        Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                        function => vec![function],
+                    };
+                    exprs.push(arg);
+                    return Ok(Expr::Group(exprs));
+                }
+                (Kind::Const(expr), 0) => return Ok(expr.clone()),
+                // a path that leaves from an auxiliary port does not reduce the next pair
+                (Kind::Fan(level), 0) => {
+                    let level = *level;
+                    back = None;
+                    let Some(Mark::Side(slot)) = context.level(level).pop() else {
+                        return Err(Stop::Broken);
+                    };
+                    from = port(node, slot);
+                }
+                (Kind::Fan(level), _) => {
+                    back = self.back(port(node, 0), from, &context);
+                    context.level(*level).push(Mark::Side(slot));
+                    from = port(node, 0);
+                }
+                (Kind::Scope(level), 0) => {
+                    back = None;
+                    context.enter(*level);
+                    from = port(node, 1);
+                }
+                (Kind::Scope(level), _) => {
+                    back = self.back(port(node, 0), from, &context);
+                    context.leave(*level);
+                    from = port(node, 0);
+                }
+                _ => return Err(Stop::Broken),
+            }
+        }
+    }
+
+    /// Reduces the net while it is read back, does not return if the term has no normal form.
+    pub fn normalize(&mut self) -> Option<Expr> {
+        loop {
+            match self.read_back(port(0, 0), Context::default(), &mut Vec::default()) {
+                Ok(expr) => return Some(expr),
+                Err(Stop::Again) => {}
+                Err(Stop::Broken) => {
+                    eprintln!("The net cannot be read back");
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// The normal form with the number of interactions and beta reductions.
+pub fn normalize(expr: &Expr) -> Option<(Expr, usize, usize)> {
+    let mut net = Net::new(expr)?;
+    let expr = net.normalize()?;
+    Some((expr, net.interactions, net.betas))
+}
+
+#[test]
+fn same_as_nbe() {
+    for text in [
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        // sub 10 6
+        "((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f(f(f(f(f(f(f(f x))))))))))) (f:x:(f(f(f(f(f(f x))))))))",
+        "((a:b:b) ((a:(a a)) (a:(a a))))",
+        "((a:b:(a (a b))) 'F 'X)",
+        "x:((y:y) x)",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        let (normal, _, _) = normalize(&expr).unwrap();
+        assert!(normal.same(&crate::nbe::normalize(&expr)));
+    }
+}
+
+#[test]
+fn church_tower() {
+    // 2 2 2 = 16
+    let expr = Expr::Group(vec![Expr::from(2), Expr::from(2), Expr::from(2)]);
+    let (normal, interactions, betas) = normalize(&expr).unwrap();
+    assert!(normal.same(&Expr::from(16)));
+
+    let (_, shared) = crate::graph::normalize(&expr);
+    assert!(betas < shared);
+    assert!(interactions > betas);
+
+    // 2 2 2 2 = 65536, the normal form is deep and everything on `Expr` is recursive
+    let tower = std::thread::Builder::new()
+        .stack_size(1 << 30)
+        .spawn(|| {
+            let expr = Expr::Group(vec![Expr::from(2); 4]);
+            let (normal, _, betas) = normalize(&expr).unwrap();
+            let (graph, shared) = crate::graph::normalize(&expr);
+            (
+                crate::church::numeral(&normal),
+                betas,
+                normal.same(&graph),
+                shared,
+            )
+        })
+        .unwrap();
+    let (num, betas, same, shared) = tower.join().unwrap();
+    assert_eq!(num, Some(65536));
+    assert!(same);
+    assert!(betas < shared);
+}
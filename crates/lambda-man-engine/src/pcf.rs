@@ -0,0 +1,694 @@
+// PCF-style literals
+//
+// Integers, booleans and strings are values of their own and the operators on them are done in one
+// step, a delta rule, instead of many beta reductions on encodings.
+//
+// \x. b                      is a function
+// 12  true  false  "text"    are literals
+// a + b  a - b  a * b        on integers, `+` joins strings too
+// a == b                     on literals of the same kind
+// if c then a else b         only the condition is reduced until it is a boolean
+//
+// Without the strings, a term can be encoded with Church numerals and booleans to compare the
+// native evaluation with the beta reductions of the encoding. The types of the terms are inferred
+// to choose the encoding of `==` on integers or on booleans. The integers need to be natural
+// numbers there and `-` of the numerals stops at 0, so `a - b` is encoded only when it cannot be
+// negative: `a` and `b` are made of literals and `a` is not less than `b`.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Eq,
+}
+
+impl Op {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Eq => "==",
+        }
+    }
+
+    fn apply(self, a: &Term, b: &Term) -> Option<Term> {
+        Some(match (self, a, b) {
+            (Op::Add, Term::Int(a), Term::Int(b)) => Term::Int(a.checked_add(*b)?),
+            (Op::Add, Term::Str(a), Term::Str(b)) => Term::Str(format!("{a}{b}")),
+            (Op::Sub, Term::Int(a), Term::Int(b)) => Term::Int(a.checked_sub(*b)?),
+            (Op::Mul, Term::Int(a), Term::Int(b)) => Term::Int(a.checked_mul(*b)?),
+            (Op::Eq, Term::Int(_), Term::Int(_))
+            | (Op::Eq, Term::Bool(_), Term::Bool(_))
+            | (Op::Eq, Term::Str(_), Term::Str(_)) => Term::Bool(a == b),
+            _ => return None,
+        })
+    }
+
+    /// The function on the Church encodings of the operands, the type of the operands of `+` is
+    /// not known when they are not used.
+    fn church(self, operands: &Type) -> Option<&'static str> {
+        Some(match (self, operands) {
+            (Op::Add, Type::Int | Type::Var(_)) => "(a:b:c:d:(a c (b c d)))",
+            (Op::Sub, Type::Int) => SUB,
+            (Op::Mul, Type::Int) => "(a:b:c:(a (b c)))",
+            (Op::Eq, Type::Int) => EQ,
+            (Op::Eq, Type::Bool) => "(p:q:(p q (q (a:b:b) (a:b:a))))",
+            _ => return None,
+        })
+    }
+}
+
+const SUB: &str = "(m:n:(n (n:f:x:(n (g:h:(h (g f))) (u:x) (u:u))) m))";
+const EQ: &str = "(m:n:((p:q:(p q p)) \
+    ((k:(k (x:a:b:b) (a:b:a))) ((m:n:(n (n:f:x:(n (g:h:(h (g f))) (u:x) (u:u))) m)) m n)) \
+    ((k:(k (x:a:b:b) (a:b:a))) ((m:n:(n (n:f:x:(n (g:h:(h (g f))) (u:x) (u:u))) m)) n m))))";
+
+/// The types of the terms, only to choose the encodings.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Int,
+    Bool,
+    Str,
+    Var(usize),
+    Arrow(Box<Type>, Box<Type>),
+}
+
+#[derive(Default)]
+struct Types {
+    vars: Vec<Option<Type>>,
+    /// The type of the operands of every operator, after the operands.
+    operands: Vec<Type>,
+}
+
+impl Types {
+    fn fresh(&mut self) -> Type {
+        self.vars.push(None);
+        Type::Var(self.vars.len() - 1)
+    }
+
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(id) => match &self.vars[*id] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            Type::Arrow(from, to) => {
+                Type::Arrow(Box::new(self.resolve(from)), Box::new(self.resolve(to)))
+            }
+            ty => ty.clone(),
+        }
+    }
+
+    fn occurs(&self, id: usize, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Var(other) => other == id,
+            Type::Arrow(from, to) => self.occurs(id, &from) || self.occurs(id, &to),
+            _ => false,
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Option<()> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Var(a), Type::Var(b)) if a == b => Some(()),
+            (Type::Var(id), ty) | (ty, Type::Var(id)) => {
+                if self.occurs(id, &ty) {
+                    return None;
+                }
+                self.vars[id] = Some(ty);
+                Some(())
+            }
+            (Type::Arrow(a, b), Type::Arrow(c, d)) => {
+                self.unify(&a, &c)?;
+                self.unify(&b, &d)
+            }
+            (a, b) => (a == b).then_some(()),
+        }
+    }
+
+    fn infer(&mut self, term: &Term, env: &mut Vec<Type>) -> Option<Type> {
+        Some(match term {
+            Term::Int(_) => Type::Int,
+            Term::Bool(_) => Type::Bool,
+            Term::Str(_) => Type::Str,
+            Term::Var(id) => env[env.len().checked_sub(*id as usize + 1)?].clone(),
+            Term::Lam(_, body) => {
+                let var = self.fresh();
+                env.push(var.clone());
+                let body = self.infer(body, env);
+                env.pop();
+                Type::Arrow(Box::new(var), Box::new(body?))
+            }
+            Term::App(function, argument) => {
+                let function = self.infer(function, env)?;
+                let argument = self.infer(argument, env)?;
+                let result = self.fresh();
+                let arrow = Type::Arrow(Box::new(argument), Box::new(result.clone()));
+                self.unify(&function, &arrow)?;
+                result
+            }
+            Term::Op(op, a, b) => {
+                let a = self.infer(a, env)?;
+                let b = self.infer(b, env)?;
+                self.unify(&a, &b)?;
+                if matches!(op, Op::Sub | Op::Mul) {
+                    self.unify(&a, &Type::Int)?;
+                }
+                self.operands.push(a.clone());
+                match op {
+                    Op::Eq => Type::Bool,
+                    Op::Add | Op::Sub | Op::Mul => a,
+                }
+            }
+            Term::If(condition, then, other) => {
+                let condition = self.infer(condition, env)?;
+                self.unify(&condition, &Type::Bool)?;
+                let then = self.infer(then, env)?;
+                let other = self.infer(other, env)?;
+                self.unify(&then, &other)?;
+                then
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    Beta,
+    Delta(Op),
+    If,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Beta => "beta",
+            Rule::Delta(op) => op.symbol(),
+            Rule::If => "if",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Int(i64),
+    Bool(bool),
+    Str(String),
+    Var(u32),
+    Lam(String, Box<Term>),
+    App(Box<Term>, Box<Term>),
+    Op(Op, Box<Term>, Box<Term>),
+    If(Box<Term>, Box<Term>, Box<Term>),
+}
+
+impl Term {
+    fn app(function: Term, argument: Term) -> Term {
+        Term::App(Box::new(function), Box::new(argument))
+    }
+
+    fn op(op: Op, a: Term, b: Term) -> Term {
+        Term::Op(op, Box::new(a), Box::new(b))
+    }
+
+    fn is_literal(&self) -> bool {
+        matches!(self, Term::Int(_) | Term::Bool(_) | Term::Str(_))
+    }
+
+    pub fn format(&self) -> String {
+        self._format(&mut Vec::default())
+    }
+
+    fn _format(&self, names: &mut Vec<String>) -> String {
+        let inner = |term: &Term, names: &mut Vec<String>| match term {
+            Term::Lam(..) | Term::Op(..) | Term::If(..) => format!("({})", term._format(names)),
+            _ => term._format(names),
+        };
+
+        match self {
+            Term::Int(num) => num.to_string(),
+            Term::Bool(bool) => bool.to_string(),
+            Term::Str(text) => format!("{text:?}"),
+            Term::Var(id) => match names.len().checked_sub(*id as usize + 1) {
+                Some(i) => names[i].clone(),
+                None => format!("!{id}!"),
+            },
+            Term::Lam(name, body) => {
+                names.push(name.clone());
+                let body = body._format(names);
+                names.pop();
+                format!("\\{name}. {body}")
+            }
+            Term::App(function, argument) => {
+                let function = inner(function, names);
+                match argument.as_ref() {
+                    Term::App(..) => format!("{function} ({})", argument._format(names)),
+                    _ => format!("{function} {}", inner(argument, names)),
+                }
+            }
+            Term::Op(op, a, b) => {
+                format!("{} {} {}", inner(a, names), op.symbol(), inner(b, names))
+            }
+            Term::If(condition, then, other) => format!(
+                "if {} then {} else {}",
+                condition._format(names),
+                then._format(names),
+                other._format(names)
+            ),
+        }
+    }
+
+    /// Adds `by` to every variable that is not bound inside of the term.
+    fn shift(&self, by: u32, depth: u32) -> Term {
+        self.map(depth, &|id, depth| match id >= depth {
+            true => Term::Var(id + by),
+            false => Term::Var(id),
+        })
+    }
+
+    /// Replaces the variable `depth` with `value` and removes it.
+    fn subst(&self, depth: u32, value: &Term) -> Term {
+        self.map(depth, &|id, depth| match id.cmp(&depth) {
+            std::cmp::Ordering::Equal => value.shift(depth, 0),
+            std::cmp::Ordering::Greater => Term::Var(id - 1),
+            std::cmp::Ordering::Less => Term::Var(id),
+        })
+    }
+
+    fn map(&self, depth: u32, var: &dyn Fn(u32, u32) -> Term) -> Term {
+        match self {
+            Term::Var(id) => var(*id, depth),
+            Term::Lam(name, body) => Term::Lam(name.clone(), Box::new(body.map(depth + 1, var))),
+            Term::App(function, argument) => {
+                Term::app(function.map(depth, var), argument.map(depth, var))
+            }
+            Term::Op(op, a, b) => Term::op(*op, a.map(depth, var), b.map(depth, var)),
+            Term::If(condition, then, other) => Term::If(
+                Box::new(condition.map(depth, var)),
+                Box::new(then.map(depth, var)),
+                Box::new(other.map(depth, var)),
+            ),
+            term => term.clone(),
+        }
+    }
+
+    /// Performs the leftmost outermost beta or delta reduction, returns the rule that was used.
+    pub fn step(&mut self) -> Option<Rule> {
+        match self {
+            Term::App(function, argument) => {
+                if let Term::Lam(_, body) = function.as_ref() {
+                    *self = body.subst(0, argument);
+                    return Some(Rule::Beta);
+                }
+                function.step().or_else(|| argument.step())
+            }
+            Term::Lam(_, body) => body.step(),
+            Term::Op(op, a, b) => {
+                if a.is_literal()
+                    && b.is_literal()
+                    && let Some(result) = op.apply(a, b)
+                {
+                    let op = *op;
+                    *self = result;
+                    return Some(Rule::Delta(op));
+                }
+                a.step().or_else(|| b.step())
+            }
+            Term::If(condition, then, other) => {
+                if let Term::Bool(bool) = condition.as_ref() {
+                    *self = if *bool { *then.clone() } else { *other.clone() };
+                    return Some(Rule::If);
+                }
+                condition.step()
+            }
+            Term::Int(_) | Term::Bool(_) | Term::Str(_) | Term::Var(_) => None,
+        }
+    }
+
+    /// The integer of a term that is made of literals.
+    fn constant(&self) -> Option<i64> {
+        match self {
+            Term::Int(num) => Some(*num),
+            Term::Op(op @ (Op::Add | Op::Sub | Op::Mul), a, b) => {
+                match op.apply(&Term::Int(a.constant()?), &Term::Int(b.constant()?))? {
+                    Term::Int(num) => Some(num),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The Church encoding, strings, negative integers, terms that have no type and `-` that can
+    /// be negative have none.
+    pub fn to_expr(&self) -> Option<Expr> {
+        let mut types = Types::default();
+        types.infer(self, &mut Vec::default())?;
+        let mut operands = types.operands.iter().map(|ty| types.resolve(ty));
+        self.encode(&mut operands)
+    }
+
+    /// `operands` are the types of the operands of the operators, in the order of `Types::infer`.
+    fn encode(&self, operands: &mut impl Iterator<Item = Type>) -> Option<Expr> {
+        Some(match self {
+            Term::Int(num) => Expr::from(usize::try_from(*num).ok()?),
+            Term::Bool(true) => Expr::parse("a:b:a")?,
+            Term::Bool(false) => Expr::parse("a:b:b")?,
+            Term::Str(_) => return None,
+            Term::Var(id) => Expr::Relative(*id),
+            Term::Lam(_, body) => Expr::Def(Box::new(body.encode(operands)?)),
+            Term::App(function, argument) => {
+                Expr::Group(vec![function.encode(operands)?, argument.encode(operands)?])
+            }
+            Term::Op(op, a, b) => {
+                let (ea, eb) = (a.encode(operands)?, b.encode(operands)?);
+                let function = op.church(&operands.next()?)?;
+                if *op == Op::Sub && a.constant()? < b.constant()? {
+                    return None;
+                }
+                Expr::Group(vec![Expr::parse(function)?, ea, eb])
+            }
+            Term::If(condition, then, other) => Expr::Group(vec![
+                condition.encode(operands)?,
+                then.encode(operands)?,
+                other.encode(operands)?,
+            ]),
+        })
+    }
+
+    /// Reads a normal form of the Church encoding as a literal of the same kind as this one.
+    pub fn decode(&self, expr: &Expr) -> Option<Term> {
+        match self {
+            Term::Int(_) => Some(Term::Int(crate::church::numeral(expr)?.try_into().ok()?)),
+            Term::Bool(_) => [true, false]
+                .into_iter()
+                .find(|bool| Term::Bool(*bool).to_expr().is_some_and(|b| b.same(expr)))
+                .map(Term::Bool),
+            _ => None,
+        }
+    }
+}
+
+/// The normal form and how many steps were made, does not return if the term has none.
+pub fn evaluate(term: &Term) -> (Term, usize) {
+    let mut term = term.clone();
+    let mut steps = 0;
+    while term.step().is_some() {
+        steps += 1;
+    }
+    (term, steps)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Str(String),
+    Lambda,
+    Dot,
+    Open,
+    Close,
+    Op(Op),
+    If,
+    Then,
+    Else,
+    True,
+    False,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut out = Vec::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '\\' | 'λ' => Token::Lambda,
+            '.' => Token::Dot,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Eq),
+            '"' => {
+                let mut text = String::default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.push(chars.next()?),
+                        Some(ch) => text.push(ch),
+                        None => {
+                            eprintln!("The string does not end");
+                            return None;
+                        }
+                    }
+                }
+                Token::Str(text)
+            }
+            '0'..='9' => {
+                let mut num = String::from(ch);
+                while let Some(ch) = chars.next_if(char::is_ascii_digit) {
+                    num.push(ch);
+                }
+                Token::Int(num.parse().ok()?)
+            }
+            '_' | 'a'..='z' | 'A'..='Z' => {
+                let mut name = String::from(ch);
+                while let Some(ch) = chars.next_if(|ch| ch.is_alphanumeric() || *ch == '_') {
+                    name.push(ch);
+                }
+                match name.as_str() {
+                    "if" => Token::If,
+                    "then" => Token::Then,
+                    "else" => Token::Else,
+                    "true" => Token::True,
+                    "false" => Token::False,
+                    _ => Token::Ident(name),
+                }
+            }
+            ch => {
+                eprintln!("Unexpected `{ch}`");
+                return None;
+            }
+        };
+        out.push(token);
+    }
+
+    Some(out)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    i: usize,
+    scope: Vec<String>,
+}
+
+impl Parser {
+    fn next_if(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.i) == Some(token);
+        if found {
+            self.i += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        if self.next_if(&token) {
+            Some(())
+        } else {
+            eprintln!("Expected {token:?} at token {}", self.i);
+            None
+        }
+    }
+
+    fn term(&mut self) -> Option<Term> {
+        if self.next_if(&Token::Lambda) {
+            let Some(Token::Ident(name)) = self.tokens.get(self.i).cloned() else {
+                eprintln!("Expected a name at token {}", self.i);
+                return None;
+            };
+            self.i += 1;
+            self.expect(Token::Dot)?;
+
+            self.scope.push(name.clone());
+            let body = self.term();
+            self.scope.pop();
+            return Some(Term::Lam(name, Box::new(body?)));
+        }
+
+        if self.next_if(&Token::If) {
+            let condition = self.term()?;
+            self.expect(Token::Then)?;
+            let then = self.term()?;
+            self.expect(Token::Else)?;
+            let other = self.term()?;
+            return Some(Term::If(
+                Box::new(condition),
+                Box::new(then),
+                Box::new(other),
+            ));
+        }
+
+        let a = self.sum()?;
+        if self.next_if(&Token::Op(Op::Eq)) {
+            return Some(Term::op(Op::Eq, a, self.sum()?));
+        }
+        Some(a)
+    }
+
+    fn sum(&mut self) -> Option<Term> {
+        let mut out = self.product()?;
+        loop {
+            let op = match self.tokens.get(self.i) {
+                Some(Token::Op(op @ (Op::Add | Op::Sub))) => *op,
+                _ => return Some(out),
+            };
+            self.i += 1;
+            out = Term::op(op, out, self.product()?);
+        }
+    }
+
+    fn product(&mut self) -> Option<Term> {
+        let mut out = self.app()?;
+        while self.next_if(&Token::Op(Op::Mul)) {
+            out = Term::op(Op::Mul, out, self.app()?);
+        }
+        Some(out)
+    }
+
+    fn app(&mut self) -> Option<Term> {
+        let mut out = self.atom()?;
+        while let Some(next) = self.tokens.get(self.i)
+            && matches!(
+                next,
+                Token::Ident(_)
+                    | Token::Int(_)
+                    | Token::Str(_)
+                    | Token::True
+                    | Token::False
+                    | Token::Open
+            )
+        {
+            out = Term::app(out, self.atom()?);
+        }
+        Some(out)
+    }
+
+    fn atom(&mut self) -> Option<Term> {
+        let token = self.tokens.get(self.i).cloned();
+        self.i += 1;
+
+        match token? {
+            Token::Ident(name) => {
+                let Some(id) = self.scope.iter().rev().position(|n| *n == name) else {
+                    eprintln!("Cannot find name `{name}` in {:?}", self.scope);
+                    return None;
+                };
+                Some(Term::Var(id as u32))
+            }
+            Token::Int(num) => Some(Term::Int(num)),
+            Token::Str(text) => Some(Term::Str(text)),
+            Token::True => Some(Term::Bool(true)),
+            Token::False => Some(Term::Bool(false)),
+            Token::Open => {
+                let term = self.term()?;
+                self.expect(Token::Close)?;
+                Some(term)
+            }
+            token => {
+                eprintln!("Unexpected {token:?} at token {}", self.i - 1);
+                None
+            }
+        }
+    }
+}
+
+/// Parses a term with literals, like `(\x. x * x + 1) 3`.
+pub fn parse(text: &str) -> Option<Term> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        i: 0,
+        scope: Vec::default(),
+    };
+    let term = parser.term()?;
+
+    if parser.i != parser.tokens.len() {
+        eprintln!(
+            "Unexpected {:?} at token {}",
+            parser.tokens[parser.i], parser.i
+        );
+        return None;
+    }
+
+    Some(term)
+}
+
+#[test]
+fn delta() {
+    let term = parse("(\\x. \\y. if x == y then x * 2 else y - x) 3 4").unwrap();
+    assert_eq!(
+        term.format(),
+        "(\\x. \\y. if x == y then x * 2 else y - x) 3 4"
+    );
+
+    let mut term = term;
+    let mut rules = Vec::default();
+    while let Some(rule) = term.step() {
+        rules.push(rule);
+    }
+    assert_eq!(term, Term::Int(1));
+    assert_eq!(
+        rules,
+        [
+            Rule::Beta,
+            Rule::Beta,
+            Rule::Delta(Op::Eq),
+            Rule::If,
+            Rule::Delta(Op::Sub)
+        ]
+    );
+
+    let (term, _) = evaluate(&parse("\"lambda\" + \"-\" + \"man\" == \"lambda-man\"").unwrap());
+    assert_eq!(term, Term::Bool(true));
+
+    // not the same kind
+    let (term, steps) = evaluate(&parse("1 + true").unwrap());
+    assert_eq!((term.format().as_str(), steps), ("1 + true", 0));
+}
+
+#[test]
+fn church() {
+    for (text, result) in [
+        ("(\\x. x * x + 1) 3", Term::Int(10)),
+        ("(\\x. if x == 2 then 7 - 2 + x else 0) 2", Term::Int(7)),
+        ("(\\b. b == false) (2 == 3)", Term::Bool(true)),
+        ("true == (1 == 1)", Term::Bool(true)),
+        ("2 * 3 == 6", Term::Bool(true)),
+        ("4 == 5", Term::Bool(false)),
+    ] {
+        let term = parse(text).unwrap();
+        let (native, _) = evaluate(&term);
+        assert_eq!(native, result);
+
+        let expr = crate::nbe::normalize(&term.to_expr().unwrap());
+        assert_eq!(native.decode(&expr), Some(result));
+    }
+
+    assert_eq!(parse("\"a\"").unwrap().to_expr(), None);
+
+    // `-` that is negative, or not known to be positive, is not encoded
+    assert_eq!(evaluate(&parse("2 - 3").unwrap()).0, Term::Int(-1));
+    assert_eq!(parse("2 - 3").unwrap().to_expr(), None);
+    assert_eq!(parse("(\\x. 7 - x) 2").unwrap().to_expr(), None);
+    assert!(parse("3 - 2").unwrap().to_expr().is_some());
+
+    // `==` on booleans is not the equality of numerals
+    let term = parse("true == false").unwrap();
+    let expr = crate::nbe::normalize(&term.to_expr().unwrap());
+    assert_eq!(Term::Bool(true).decode(&expr), Some(Term::Bool(false)));
+    assert_eq!(parse("(\\x. \\y. x == y)").unwrap().to_expr(), None);
+    assert_eq!(parse("1 == true").unwrap().to_expr(), None);
+}
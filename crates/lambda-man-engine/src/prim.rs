@@ -0,0 +1,208 @@
+// Host primitives
+//
+// A label can be bound to a Rust function with an arity. When the label is the first element of a
+// group with at least that many arguments and all of them are closed and in normal form, the
+// function is called and its result is put in place of the label and the arguments. The function
+// returns `None` when it does not accept the arguments, then the label stays as it is.
+//
+// The arguments are called first, so a primitive gets the results of the primitives in its
+// arguments. The standard primitives work on integers that are labels, `'12` or `'-3`.
+
+use std::collections::HashMap;
+
+use crate::{Expr, church};
+
+type Function = Box<dyn Fn(&[Expr]) -> Option<Expr>>;
+
+pub struct Primitive {
+    pub arity: usize,
+    function: Function,
+}
+
+/// A primitive that was called.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub result: Expr,
+}
+
+impl Call {
+    pub fn format(&self) -> String {
+        let mut out = format!("'{}", self.name);
+        for arg in &self.args {
+            out.push(' ');
+            out.push_str(&arg.format(0));
+        }
+        out.push_str(" = ");
+        out.push_str(&self.result.format(0));
+        out
+    }
+}
+
+#[derive(Default)]
+pub struct Primitives {
+    primitives: HashMap<String, Primitive>,
+}
+
+impl Primitives {
+    /// `ADD`, `SUB`, `MUL` and `EQ` on integers, `INT` and `CHURCH` convert between integers and
+    /// Church numerals.
+    pub fn standard() -> Primitives {
+        let mut primitives = Primitives::default();
+
+        let arith = |op: fn(i64, i64) -> Option<i64>| {
+            move |args: &[Expr]| Some(int_label(op(int(&args[0])?, int(&args[1])?)?))
+        };
+        primitives.register("ADD", 2, arith(i64::checked_add));
+        primitives.register("SUB", 2, arith(i64::checked_sub));
+        primitives.register("MUL", 2, arith(i64::checked_mul));
+        primitives.register("EQ", 2, |args| {
+            let text = if args[0].same(&args[1]) {
+                "a:b:a"
+            } else {
+                "a:b:b"
+            };
+            Expr::parse(text)
+        });
+        primitives.register("INT", 1, |args| {
+            Some(int_label(church::numeral(&args[0])?.try_into().ok()?))
+        });
+        primitives.register("CHURCH", 1, |args| {
+            let num = usize::try_from(int(&args[0])?).ok()?;
+            (num <= church::LIMIT).then(|| Expr::from(num))
+        });
+
+        primitives
+    }
+
+    /// Binds the label `'name` to the function, the arity needs to be at least one.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        arity: usize,
+        function: impl Fn(&[Expr]) -> Option<Expr> + 'static,
+    ) {
+        assert!(arity > 0, "A primitive needs an argument");
+        self.primitives.insert(
+            name.into(),
+            Primitive {
+                arity,
+                function: Box::new(function),
+            },
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Primitive> {
+        self.primitives.get(name)
+    }
+
+    /// The names and arities of the primitives, sorted by name.
+    pub fn list(&self) -> Vec<(&str, usize)> {
+        let mut list = self
+            .primitives
+            .iter()
+            .map(|(name, primitive)| (name.as_str(), primitive.arity))
+            .collect::<Vec<_>>();
+        list.sort();
+        list
+    }
+
+    /// Calls the innermost leftmost primitive that has its arguments.
+    pub fn call(&self, expr: &mut Expr) -> Option<Call> {
+        let exprs = match expr {
+            Expr::Group(exprs) => exprs,
+            Expr::Def(body) => return self.call(body),
+            Expr::Relative(_) | Expr::Label(_) => return None,
+        };
+
+        for expr in exprs.iter_mut() {
+            if let Some(call) = self.call(expr) {
+                return Some(call);
+            }
+        }
+
+        let Some(Expr::Label(name)) = exprs.first().map(Expr::unwrap) else {
+            return None;
+        };
+        let primitive = self.get(name)?;
+        let args = exprs.get(1..=primitive.arity)?;
+        if !args
+            .iter()
+            .all(|arg| arg.is_contained(0) && arg.find_beta_reductions().is_empty())
+        {
+            return None;
+        }
+
+        let result = (primitive.function)(args)?;
+        let name = name.clone();
+        let args = exprs
+            .splice(..=primitive.arity, [result.clone()])
+            .skip(1)
+            .collect();
+        if exprs.len() == 1 {
+            *expr = exprs.remove(0);
+        }
+
+        Some(Call { name, args, result })
+    }
+}
+
+/// The integer if the expression is a label like `'12`.
+pub fn int(expr: &Expr) -> Option<i64> {
+    match expr.unwrap() {
+        Expr::Label(name) => name.parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn int_label(num: i64) -> Expr {
+    Expr::Label(num.to_string())
+}
+
+#[test]
+fn standard() {
+    let primitives = Primitives::standard();
+
+    let mut expr = Expr::parse("('ADD ('MUL '6 '7) ((a:a) '1))").unwrap();
+    let call = primitives.call(&mut expr).unwrap();
+    assert_eq!(call.format(), "'MUL '6 '7 = '42");
+
+    // the argument is not in normal form
+    assert!(primitives.call(&mut expr).is_none());
+    expr.beta_reduction_at(&expr.find_beta_reductions()[0].1);
+    let call = primitives.call(&mut expr).unwrap();
+    assert_eq!(call.result, int_label(43));
+    assert_eq!(expr, int_label(43));
+
+    let mut expr = Expr::parse("('EQ 'X 'X 'TRUE 'FALSE)").unwrap();
+    primitives.call(&mut expr).unwrap();
+    assert!(crate::nbe::normalize(&expr).same(&Expr::parse("'TRUE").unwrap()));
+
+    let mut expr = Expr::Group(vec![Expr::parse("'INT").unwrap(), Expr::from(5)]);
+    primitives.call(&mut expr).unwrap();
+    assert_eq!(int(&expr), Some(5));
+
+    // not enough arguments and not integers
+    let mut expr = Expr::parse("('SUB '1)").unwrap();
+    assert!(primitives.call(&mut expr).is_none());
+    let mut expr = Expr::parse("('SUB 'A '1)").unwrap();
+    assert!(primitives.call(&mut expr).is_none());
+}
+
+#[test]
+fn register() {
+    let mut primitives = Primitives::default();
+    primitives.register("TWICE", 1, |args| {
+        Some(Expr::Group(vec![args[0].clone(), args[0].clone()]))
+    });
+    assert_eq!(primitives.list(), [("TWICE", 1)]);
+
+    // the bound variable is not closed
+    let mut expr = Expr::parse("a:('TWICE a)").unwrap();
+    assert!(primitives.call(&mut expr).is_none());
+
+    let mut expr = Expr::parse("('TWICE a:a 'X)").unwrap();
+    primitives.call(&mut expr).unwrap();
+    assert!(expr.same(&Expr::parse("((a:a) (a:a) 'X)").unwrap()));
+}
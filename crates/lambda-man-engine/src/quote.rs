@@ -0,0 +1,100 @@
+// Quote
+//
+// The quote of a term is the term as data, with the encoding of Mogensen. There are three cases
+// and the functions are functions of the meta level, so the substitution is done by the beta
+// reductions of the interpreter:
+//
+// ⌜x⌝     = a:b:c:(a x)
+// ⌜M N⌝   = a:b:c:(b ⌜M⌝ ⌜N⌝)
+// ⌜x:M⌝   = a:b:c:(c x:⌜M⌝)
+//
+// A label or `()` is quoted like a variable. `eval` of the prelude is the self-interpreter, so
+// `(eval ⌜M⌝)` has the normal form of M.
+
+use crate::Expr;
+
+pub fn quote(expr: &Expr) -> Expr {
+    quote_at(expr, 0, &mut Vec::default())
+}
+
+/// `depth` is the number of functions above, `vars` are the depths of the functions of the
+/// variables of the term.
+fn quote_at(expr: &Expr, depth: u32, vars: &mut Vec<u32>) -> Expr {
+    match expr {
+        Expr::Group(exprs) if !exprs.is_empty() => quote_app(exprs, depth, vars),
+        Expr::Def(body) => {
+            vars.push(depth + 3);
+            let body = quote_at(body, depth + 4, vars);
+            vars.pop();
+            constructor(Expr::Group(vec![
+                Expr::Relative(0),
+                Expr::Def(Box::new(body)),
+            ]))
+        }
+        Expr::Relative(id) => {
+            let id = match vars.len().checked_sub(*id as usize + 1) {
+                Some(i) => depth + 3 - 1 - vars[i],
+                // free, above all the functions
+                None => id - vars.len() as u32 + depth + 3,
+            };
+            constructor(Expr::Group(vec![Expr::Relative(2), Expr::Relative(id)]))
+        }
+        Expr::Group(_) | Expr::Label(_) => {
+            constructor(Expr::Group(vec![Expr::Relative(2), expr.clone()]))
+        }
+    }
+}
+
+/// The applications are to the left, `(f a b)` is `((f a) b)`.
+fn quote_app(exprs: &[Expr], depth: u32, vars: &mut Vec<u32>) -> Expr {
+    let Some((last, rest)) = exprs.split_last().filter(|(_, rest)| !rest.is_empty()) else {
+        return quote_at(&exprs[0], depth, vars);
+    };
+    constructor(Expr::Group(vec![
+        Expr::Relative(1),
+        quote_app(rest, depth + 3, vars),
+        quote_at(last, depth + 3, vars),
+    ]))
+}
+
+/// `a:b:c:body`
+fn constructor(body: Expr) -> Expr {
+    Expr::Def(Box::new(Expr::Def(Box::new(Expr::Def(Box::new(body))))))
+}
+
+#[test]
+fn quotes() {
+    assert_eq!(
+        quote(&Expr::parse("a:a").unwrap()).format(0),
+        "a:b:c:(c d:e:f:g:(e d))"
+    );
+    assert_eq!(
+        quote(&Expr::parse("('X 'Y)").unwrap()).format(0),
+        "a:b:c:(b d:e:f:(d 'X) d:e:f:(d 'Y))"
+    );
+    // the free variable is free in the quote
+    let expr = Expr::Def(Box::new(Expr::Relative(1)));
+    assert_eq!(quote(&expr).format(1), "b:c:d:(d e:f:g:h:(f a))");
+}
+
+#[test]
+fn self_interpreter() {
+    use crate::defs::{Definitions, Fix};
+
+    let definitions = Definitions::prelude();
+    for text in [
+        "a:a",
+        "((a:b:a) 'X 'Y)",
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        "a:b:(b (c:c a) ())",
+        "(x:((y:z:(y z)) x 'X))",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        for fix in [Fix::Y, Fix::Z] {
+            let eval = definitions.parse("eval", fix).unwrap();
+            let evaluated = Expr::Group(vec![eval, quote(&expr)]);
+            assert!(crate::nbe::normalize(&evaluated).same(&crate::nbe::normalize(&expr)));
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// Reductions
+//
+// `expr.reductions(strategy)` is an iterator over the beta reductions of a term, every item is the
+// term after a reduction, the redexes that were found and the one that was reduced. It ends at the
+// normal form, so `.last()` is the normal form, `.count()` the number of reductions and `.take(n)`
+// stops a term that has none.
+//
+// The strategy chooses the redex from the ones that are found:
+//
+// Innermost   the first redex with the highest score, the deepest one, it is the step of the REPL
+// Normal      the first redex, the leftmost outermost one, it finds the normal form when there is
+//             one, so `Y f` stops when `f` does not use its argument
+//
+// `evaluate` is for a long evaluation in a GUI or a server, it tells an observer about every
+// reduction and looks at a `Cancel` before each one, the `Cancel` can be set from another thread.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Strategy {
+    #[default]
+    Innermost,
+    Normal,
+}
+
+impl Strategy {
+    /// The index of the redex to reduce, `candidates` are from `find_beta_reductions`.
+    pub fn choose(&self, candidates: &[(u32, Vec<usize>)]) -> Option<usize> {
+        if candidates.is_empty() {
+            return None;
+        }
+        match self {
+            Strategy::Innermost => {
+                let mut max_i = 0;
+                let mut last_score = 0;
+                for (i, (score, _)) in candidates.iter().enumerate() {
+                    if *score > last_score {
+                        last_score = *score;
+                        max_i = i;
+                    }
+                }
+                Some(max_i)
+            }
+            Strategy::Normal => Some(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reduction {
+    /// The term after the reduction.
+    pub expr: Expr,
+    /// The redexes of the term before it, from `find_beta_reductions`.
+    pub candidates: Vec<(u32, Vec<usize>)>,
+    /// The index of the redex that was reduced.
+    pub chosen: usize,
+}
+
+impl Reduction {
+    /// The path of the redex in the term before it.
+    pub fn at(&self) -> &[usize] {
+        &self.candidates[self.chosen].1
+    }
+}
+
+pub struct Reductions {
+    expr: Expr,
+    strategy: Strategy,
+}
+
+impl Reductions {
+    pub fn new(mut expr: Expr, strategy: Strategy) -> Reductions {
+        expr.simplify();
+        Reductions { expr, strategy }
+    }
+}
+
+impl Iterator for Reductions {
+    type Item = Reduction;
+
+    fn next(&mut self) -> Option<Reduction> {
+        let candidates = self.expr.find_beta_reductions();
+        let chosen = self.strategy.choose(&candidates)?;
+
+        if !self.expr.beta_reduction_at(&candidates[chosen].1) {
+            return None;
+        }
+        self.expr.simplify();
+        Some(Reduction {
+            expr: self.expr.clone(),
+            candidates,
+            chosen,
+        })
+    }
+}
+
+/// Stops an evaluation, the clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn new() -> Cancel {
+        Cancel::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// What the observer is told after a reduction.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    /// The number of reductions so far.
+    pub steps: usize,
+    /// The size of the term after the reduction.
+    pub size: usize,
+    pub reduction: &'a Reduction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Normal,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    /// The normal form, or the last term when it was cancelled.
+    pub expr: Expr,
+    pub steps: usize,
+    pub outcome: Outcome,
+}
+
+/// Reduces until the normal form or until `cancel` is set, `observe` is called after every
+/// reduction.
+pub fn evaluate(
+    expr: &Expr,
+    strategy: Strategy,
+    cancel: &Cancel,
+    mut observe: impl FnMut(Progress),
+) -> Evaluation {
+    let mut reductions = expr.reductions(strategy);
+    let mut steps = 0;
+    let outcome = loop {
+        if cancel.is_cancelled() {
+            break Outcome::Cancelled;
+        }
+        let Some(reduction) = reductions.next() else {
+            break Outcome::Normal;
+        };
+        steps += 1;
+        observe(Progress {
+            steps,
+            size: reduction.expr.size(),
+            reduction: &reduction,
+        });
+    };
+
+    Evaluation {
+        expr: reductions.expr,
+        steps,
+        outcome,
+    }
+}
+
+/// The normal form with the strategy, does not return if it is not found.
+pub fn normalize(expr: &Expr, strategy: Strategy) -> Expr {
+    match expr.reductions(strategy).last() {
+        Some(reduction) => reduction.expr,
+        None => {
+            let mut expr = expr.clone();
+            expr.simplify();
+            expr
+        }
+    }
+}
+
+#[test]
+fn strategies() {
+    // add 1 2
+    let text = "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))";
+    let expr = Expr::parse(text).unwrap();
+    for strategy in [Strategy::Innermost, Strategy::Normal] {
+        let (n, last) = expr.reductions(strategy).enumerate().last().unwrap();
+        assert!(n > 0);
+        assert!(last.expr.same(&Expr::from(3)));
+        assert!(normalize(&expr, strategy).same(&Expr::from(3)));
+    }
+    assert!(normalize(&Expr::from(3), Strategy::Normal).same(&Expr::from(3)));
+
+    // the argument has no normal form and is not used
+    let expr = Expr::parse("((x:'A) ((x:(x x)) (x:(x x))))").unwrap();
+    assert_eq!(expr.reductions(Strategy::Innermost).take(50).count(), 50);
+    let mut reductions = expr.reductions(Strategy::Normal);
+    assert_eq!(
+        reductions.next().unwrap().expr,
+        Expr::parse("('A)").unwrap()
+    );
+    assert!(reductions.next().is_none());
+}
+
+#[test]
+fn cancel() {
+    // add 1 2
+    let text = "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))";
+    let expr = Expr::parse(text).unwrap();
+    let mut sizes = Vec::default();
+    let evaluation = evaluate(&expr, Strategy::Innermost, &Cancel::new(), |progress| {
+        assert_eq!(progress.steps, sizes.len() + 1);
+        sizes.push(progress.size);
+    });
+    assert_eq!(evaluation.outcome, Outcome::Normal);
+    assert_eq!(evaluation.steps, sizes.len());
+    assert_eq!(sizes.last(), Some(&Expr::from(3).size()));
+    assert!(evaluation.expr.same(&Expr::from(3)));
+
+    // stopped by the observer
+    let cancel = Cancel::new();
+    let omega = Expr::parse("((x:(x x)) (x:(x x)))").unwrap();
+    let evaluation = evaluate(&omega, Strategy::Innermost, &cancel, |progress| {
+        if progress.steps == 10 {
+            cancel.cancel();
+        }
+    });
+    assert_eq!(evaluation.outcome, Outcome::Cancelled);
+    assert_eq!(evaluation.steps, 10);
+
+    // stopped from another thread
+    let cancel = Cancel::new();
+    let worker = {
+        let cancel = cancel.clone();
+        let omega = omega.clone();
+        std::thread::spawn(move || evaluate(&omega, Strategy::Innermost, &cancel, |_| {}))
+    };
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    cancel.cancel();
+    let evaluation = worker.join().unwrap();
+    assert_eq!(evaluation.outcome, Outcome::Cancelled);
+    assert!(evaluation.expr.same(&omega));
+}
@@ -0,0 +1,385 @@
+// Combinatory logic
+//
+// I x         = x
+// K x y       = x
+// S f g x     = f x (g x)
+// B f g x     = f (g x)
+// C f g x     = f x g
+// S' c f g x  = c (f x) (g x)
+// B' c f g x  = c (f (g x))
+// C' c f g x  = c (f x) g
+// W x y       = x y y
+//
+// The optimizations of Turner are from "The Implementation of Functional Programming Languages"
+// chapter 16.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comb {
+    S,
+    K,
+    I,
+    B,
+    C,
+    SPrime,
+    BPrime,
+    CPrime,
+    W,
+}
+
+impl Comb {
+    pub fn arity(self) -> usize {
+        match self {
+            Comb::I => 1,
+            Comb::K | Comb::W => 2,
+            Comb::S | Comb::B | Comb::C => 3,
+            Comb::SPrime | Comb::BPrime | Comb::CPrime => 4,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Comb::S => "S",
+            Comb::K => "K",
+            Comb::I => "I",
+            Comb::B => "B",
+            Comb::C => "C",
+            Comb::SPrime => "S'",
+            Comb::BPrime => "B'",
+            Comb::CPrime => "C'",
+            Comb::W => "W",
+        }
+    }
+
+    /// The lambda term that the combinator is standing for.
+    pub fn to_expr(self) -> Expr {
+        let text = match self {
+            Comb::S => "a:b:c:(a c (b c))",
+            Comb::K => "a:b:a",
+            Comb::I => "a:a",
+            Comb::B => "a:b:c:(a (b c))",
+            Comb::C => "a:b:c:(a c b)",
+            Comb::SPrime => "a:b:c:d:(a (b d) (c d))",
+            Comb::BPrime => "a:b:c:d:(a (b (c d)))",
+            Comb::CPrime => "a:b:c:d:(a (b d) c)",
+            Comb::W => "a:b:(a b b)",
+        };
+        Expr::parse(text).unwrap()
+    }
+
+    fn reduce(self, mut args: Vec<Term>) -> Term {
+        let mut arg = || args.remove(0);
+        match self {
+            Comb::I => arg(),
+            Comb::K => arg(),
+            Comb::S => {
+                let (f, g, x) = (arg(), arg(), arg());
+                Term::app(Term::app(f, x.clone()), Term::app(g, x))
+            }
+            Comb::B => {
+                let (f, g, x) = (arg(), arg(), arg());
+                Term::app(f, Term::app(g, x))
+            }
+            Comb::C => {
+                let (f, g, x) = (arg(), arg(), arg());
+                Term::app(Term::app(f, x), g)
+            }
+            Comb::SPrime => {
+                let (c, f, g, x) = (arg(), arg(), arg(), arg());
+                Term::app(Term::app(c, Term::app(f, x.clone())), Term::app(g, x))
+            }
+            Comb::BPrime => {
+                let (c, f, g, x) = (arg(), arg(), arg(), arg());
+                Term::app(c, Term::app(f, Term::app(g, x)))
+            }
+            Comb::CPrime => {
+                let (c, f, g, x) = (arg(), arg(), arg(), arg());
+                Term::app(Term::app(c, Term::app(f, x)), g)
+            }
+            Comb::W => {
+                let (x, y) = (arg(), arg());
+                Term::app(Term::app(x, y.clone()), y)
+            }
+        }
+    }
+}
+
+/// How the variables are removed from the lambda terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Abstraction {
+    /// Only `S`, `K` and `I`.
+    Naive,
+    /// `S`, `K`, `I`, `B`, `C`, `S'`, `B'` and `C'`.
+    Turner,
+    /// `B`, `C`, `K` and `W`.
+    Bckw,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Comb(Comb),
+    App(Box<Term>, Box<Term>),
+    /// A variable that is free in the whole term.
+    Var(u32),
+    Label(String),
+}
+
+impl Term {
+    pub fn app(function: Term, argument: Term) -> Term {
+        Term::App(Box::new(function), Box::new(argument))
+    }
+
+    /// Translates the lambda term, groups with no elements cannot be translated.
+    pub fn from_expr(expr: &Expr, abstraction: Abstraction) -> Option<Term> {
+        match expr {
+            Expr::Group(exprs) => {
+                let (first, rest) = exprs.split_first()?;
+                let mut out = Term::from_expr(first, abstraction)?;
+                for expr in rest {
+                    out = Term::app(out, Term::from_expr(expr, abstraction)?);
+                }
+                Some(out)
+            }
+            Expr::Def(expr) => Some(Term::from_expr(expr, abstraction)?.abstract_var(abstraction)),
+            Expr::Relative(id) => Some(Term::Var(*id)),
+            Expr::Label(name) => Some(Term::Label(name.clone())),
+        }
+    }
+
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            Term::Comb(comb) => Expr::Group(vec![comb.to_expr()]),
+            Term::App(function, argument) => {
+                let mut exprs = match function.to_expr() {
+                    Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                    function => vec![function],
+                };
+                exprs.push(argument.to_expr());
+                Expr::Group(exprs)
+            }
+            Term::Var(id) => Expr::Relative(*id),
+            Term::Label(name) => Expr::Label(name.clone()),
+        }
+    }
+
+    pub fn format(&self) -> String {
+        match self {
+            Term::Comb(comb) => comb.name().to_string(),
+            Term::App(function, argument) => {
+                if let Term::App(..) = argument.as_ref() {
+                    format!("{} ({})", function.format(), argument.format())
+                } else {
+                    format!("{} {}", function.format(), argument.format())
+                }
+            }
+            Term::Var(id) => format!("!{id}!"),
+            Term::Label(name) => format!("'{name}"),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Term::App(function, argument) => function.size() + argument.size(),
+            _ => 1,
+        }
+    }
+
+    /// Has no free variables and no labels.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Term::Comb(_) => true,
+            Term::App(function, argument) => function.is_closed() && argument.is_closed(),
+            Term::Var(_) | Term::Label(_) => false,
+        }
+    }
+
+    fn uses(&self, at: u32) -> bool {
+        match self {
+            Term::App(function, argument) => function.uses(at) || argument.uses(at),
+            Term::Var(id) => *id == at,
+            _ => false,
+        }
+    }
+
+    /// Removes a variable that is not used, the variables after it are moved down.
+    fn shift_down(self) -> Term {
+        match self {
+            Term::App(function, argument) => {
+                Term::app(function.shift_down(), argument.shift_down())
+            }
+            Term::Var(id) => Term::Var(id - 1),
+            term => term,
+        }
+    }
+
+    /// Bracket abstraction of the variable `0`.
+    fn abstract_var(self, abstraction: Abstraction) -> Term {
+        if !self.uses(0) {
+            return Term::app(Term::Comb(Comb::K), self.shift_down());
+        }
+
+        match (self, abstraction) {
+            // W K x = K x x = x
+            (Term::Var(_), Abstraction::Bckw) => {
+                Term::app(Term::Comb(Comb::W), Term::Comb(Comb::K))
+            }
+            (Term::Var(_), _) => Term::Comb(Comb::I),
+            (Term::App(function, argument), Abstraction::Bckw) => bckw(*function, *argument),
+            (Term::App(function, argument), _) => {
+                let function = function.abstract_var(abstraction);
+                let argument = argument.abstract_var(abstraction);
+                match abstraction {
+                    Abstraction::Turner => turner(function, argument),
+                    _ => Term::app(Term::app(Term::Comb(Comb::S), function), argument),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the head of the application and the arguments.
+    fn unwind(self) -> (Term, Vec<Term>) {
+        let mut args = Vec::default();
+        let mut head = self;
+        while let Term::App(function, argument) = head {
+            args.push(*argument);
+            head = *function;
+        }
+        args.reverse();
+        (head, args)
+    }
+
+    fn rewind(head: Term, args: Vec<Term>) -> Term {
+        args.into_iter().fold(head, Term::app)
+    }
+
+    /// Performs the leftmost outermost reduction, returns the combinator that was reduced.
+    pub fn step(&mut self) -> Option<Comb> {
+        let (head, mut args) = std::mem::replace(self, Term::Comb(Comb::I)).unwind();
+
+        if let Term::Comb(comb) = head
+            && args.len() >= comb.arity()
+        {
+            let rest = args.split_off(comb.arity());
+            *self = Term::rewind(comb.reduce(args), rest);
+            return Some(comb);
+        }
+
+        let mut reduced = None;
+        for arg in args.iter_mut() {
+            reduced = arg.step();
+            if reduced.is_some() {
+                break;
+            }
+        }
+
+        *self = Term::rewind(head, args);
+        reduced
+    }
+}
+
+/// `S p q` with the optimizations of Turner.
+fn turner(p: Term, q: Term) -> Term {
+    use Term::{App, Comb as C};
+
+    let k = |term: &Term| match term {
+        App(k, p) if **k == C(Comb::K) => Some((**p).clone()),
+        _ => None,
+    };
+    let b = |term: &Term| match term {
+        App(bp, q) => match bp.as_ref() {
+            App(b, p) if **b == C(Comb::B) => Some(((**p).clone(), (**q).clone())),
+            _ => None,
+        },
+        _ => None,
+    };
+    let app3 =
+        |comb: Comb, a: Term, b: Term, c: Term| Term::app(Term::app(Term::app(C(comb), a), b), c);
+
+    match (k(&p), k(&q)) {
+        // S (K p) (K q) = K (p q)
+        (Some(p), Some(q)) => Term::app(C(Comb::K), Term::app(p, q)),
+        // S (K p) I = p
+        (Some(p), None) if q == C(Comb::I) => p,
+        (Some(p), None) => match b(&q) {
+            // S (K p) (B q r) = B' p q r
+            Some((q, r)) => app3(Comb::BPrime, p, q, r),
+            // S (K p) q = B p q
+            None => Term::app(Term::app(C(Comb::B), p), q),
+        },
+        (None, Some(r)) => match b(&p) {
+            // S (B p q) (K r) = C' p q r
+            Some((p, q)) => app3(Comb::CPrime, p, q, r),
+            // S p (K q) = C p q
+            None => Term::app(Term::app(C(Comb::C), p), r),
+        },
+        (None, None) => match b(&p) {
+            // S (B p q) r = S' p q r
+            Some((c, f)) => app3(Comb::SPrime, c, f, q),
+            None => Term::app(Term::app(C(Comb::S), p), q),
+        },
+    }
+}
+
+/// `[x](m n)` with `B`, `C`, `K` and `W`, `S f g` is `W (B (C f) g)`.
+fn bckw(m: Term, n: Term) -> Term {
+    let comb = |comb: Comb, a: Term, b: Term| Term::app(Term::app(Term::Comb(comb), a), b);
+
+    match (m.uses(0), n.uses(0)) {
+        (false, _) => comb(Comb::B, m.shift_down(), n.abstract_var(Abstraction::Bckw)),
+        (_, false) => comb(Comb::C, m.abstract_var(Abstraction::Bckw), n.shift_down()),
+        _ => {
+            let m = m.abstract_var(Abstraction::Bckw);
+            let n = n.abstract_var(Abstraction::Bckw);
+            Term::app(
+                Term::Comb(Comb::W),
+                comb(Comb::B, Term::app(Term::Comb(Comb::C), m), n),
+            )
+        }
+    }
+}
+
+#[test]
+fn bracket_abstraction() {
+    // add 1 2 'f 'x
+    let expr = Expr::parse("(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))) 'f 'x)").unwrap();
+
+    let naive = Term::from_expr(&expr, Abstraction::Naive).unwrap();
+    let turner = Term::from_expr(&expr, Abstraction::Turner).unwrap();
+    let bckw = Term::from_expr(&expr, Abstraction::Bckw).unwrap();
+    assert!(turner.size() < naive.size());
+
+    let result = Term::from_expr(
+        &Expr::parse("('f ('f ('f 'x)))").unwrap(),
+        Abstraction::Naive,
+    );
+
+    for mut term in [naive, turner, bckw] {
+        println!("{}", term.format());
+        while let Some(comb) = term.step() {
+            println!("{}: {}", comb.name(), term.format());
+        }
+        assert_eq!(Some(term), result);
+    }
+}
+
+#[test]
+fn back_to_lambda() {
+    let expr = Expr::parse("a:b:(b a)").unwrap();
+    let term = Term::from_expr(&expr, Abstraction::Turner).unwrap();
+    assert_eq!(term, Term::app(Term::Comb(Comb::C), Term::Comb(Comb::I)));
+
+    let expr = Expr::Group(vec![
+        term.to_expr(),
+        Expr::Label("x".into()),
+        Expr::Label("f".into()),
+    ]);
+    let expr = crate::reduce::normalize(&expr, crate::reduce::Strategy::Innermost);
+
+    let result = Expr::parse("('f 'x)").unwrap();
+    assert_eq!(
+        Term::from_expr(&expr, Abstraction::Naive),
+        Term::from_expr(&result, Abstraction::Naive)
+    );
+}
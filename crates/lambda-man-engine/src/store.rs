@@ -0,0 +1,245 @@
+// Hash-consed terms
+//
+// Every term is a node in one arena and the same node is added only once, so two terms are the
+// same when they have the same id and a term that is used many times is stored once.
+// Applications are binary, `(a b c)` and `((a b) c)` are the same node.
+//
+// The size and the free variable bound of every node are computed when it is added, a node with a
+// bound of `0` is closed. Substitution does not go into the nodes that cannot have the variable.
+
+use std::collections::HashMap;
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Id(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    App(Id, Id),
+    Lam(Id),
+    Var(u32),
+    Label(String),
+    /// `()`
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Info {
+    size: usize,
+    bound: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct Store {
+    nodes: Vec<Node>,
+    infos: Vec<Info>,
+    ids: HashMap<Node, Id>,
+}
+
+impl Store {
+    /// Returns the id of the node, it is added if it is not in the store.
+    pub fn add(&mut self, node: Node) -> Id {
+        if let Some(id) = self.ids.get(&node) {
+            return *id;
+        }
+
+        let info = match &node {
+            Node::App(function, arg) => Info {
+                size: 1 + self.size(*function) + self.size(*arg),
+                bound: self.bound(*function).max(self.bound(*arg)),
+            },
+            Node::Lam(body) => Info {
+                size: 1 + self.size(*body),
+                bound: self.bound(*body).saturating_sub(1),
+            },
+            Node::Var(id) => Info {
+                size: 1,
+                bound: id + 1,
+            },
+            Node::Label(_) | Node::Empty => Info { size: 1, bound: 0 },
+        };
+
+        let id = Id(self.nodes.len() as u32);
+        self.nodes.push(node.clone());
+        self.infos.push(info);
+        self.ids.insert(node, id);
+        id
+    }
+
+    pub fn node(&self, id: Id) -> &Node {
+        &self.nodes[id.0 as usize]
+    }
+
+    /// How many nodes the term has when nothing is shared.
+    pub fn size(&self, id: Id) -> usize {
+        self.infos[id.0 as usize].size
+    }
+
+    /// The free variables of the term are lower than this.
+    pub fn bound(&self, id: Id) -> u32 {
+        self.infos[id.0 as usize].bound
+    }
+
+    pub fn is_closed(&self, id: Id) -> bool {
+        self.bound(id) == 0
+    }
+
+    /// How many different nodes are stored.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn insert(&mut self, expr: &Expr) -> Id {
+        match expr {
+            Expr::Group(exprs) => {
+                let Some((first, rest)) = exprs.split_first() else {
+                    return self.add(Node::Empty);
+                };
+                let mut id = self.insert(first);
+                for expr in rest {
+                    let arg = self.insert(expr);
+                    id = self.add(Node::App(id, arg));
+                }
+                id
+            }
+            Expr::Def(body) => {
+                let body = self.insert(body);
+                self.add(Node::Lam(body))
+            }
+            Expr::Relative(id) => self.add(Node::Var(*id)),
+            Expr::Label(name) => self.add(Node::Label(name.clone())),
+        }
+    }
+
+    pub fn to_expr(&self, id: Id) -> Expr {
+        match self.node(id) {
+            Node::App(function, arg) => {
+                let mut exprs = match self.to_expr(*function) {
+                    Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                    function => vec![function],
+                };
+                exprs.push(self.to_expr(*arg));
+                Expr::Group(exprs)
+            }
+            Node::Lam(body) => Expr::Def(Box::new(self.to_expr(*body))),
+            Node::Var(id) => Expr::Relative(*id),
+            Node::Label(name) => Expr::Label(name.clone()),
+            Node::Empty => Expr::Group(Vec::default()),
+        }
+    }
+
+    /// Adds `by` to the variables that are not bound inside of the term, from `depth`.
+    pub fn shift(&mut self, id: Id, by: u32, depth: u32) -> Id {
+        if by == 0 || self.bound(id) <= depth {
+            return id;
+        }
+
+        match self.node(id).clone() {
+            Node::App(function, arg) => {
+                let function = self.shift(function, by, depth);
+                let arg = self.shift(arg, by, depth);
+                self.add(Node::App(function, arg))
+            }
+            Node::Lam(body) => {
+                let body = self.shift(body, by, depth + 1);
+                self.add(Node::Lam(body))
+            }
+            Node::Var(var) => self.add(Node::Var(var + by)),
+            Node::Label(_) | Node::Empty => id,
+        }
+    }
+
+    /// The body of a function with its variable replaced by `value`.
+    pub fn substitute(&mut self, body: Id, value: Id) -> Id {
+        self._substitute(body, 0, value, &mut HashMap::default())
+    }
+
+    fn _substitute(
+        &mut self,
+        id: Id,
+        depth: u32,
+        value: Id,
+        done: &mut HashMap<(Id, u32), Id>,
+    ) -> Id {
+        if self.bound(id) <= depth {
+            return id;
+        }
+        if let Some(id) = done.get(&(id, depth)) {
+            return *id;
+        }
+
+        let out = match self.node(id).clone() {
+            Node::App(function, arg) => {
+                let function = self._substitute(function, depth, value, done);
+                let arg = self._substitute(arg, depth, value, done);
+                self.add(Node::App(function, arg))
+            }
+            Node::Lam(body) => {
+                let body = self._substitute(body, depth + 1, value, done);
+                self.add(Node::Lam(body))
+            }
+            Node::Var(var) if var == depth => self.shift(value, depth, 0),
+            Node::Var(var) => self.add(Node::Var(var - 1)),
+            Node::Label(_) | Node::Empty => id,
+        };
+        done.insert((id, depth), out);
+        out
+    }
+
+    /// Reduces the term if it is a function applied to an argument.
+    pub fn beta(&mut self, id: Id) -> Option<Id> {
+        let Node::App(function, arg) = *self.node(id) else {
+            return None;
+        };
+        let Node::Lam(body) = *self.node(function) else {
+            return None;
+        };
+        Some(self.substitute(body, arg))
+    }
+}
+
+#[test]
+fn sharing() {
+    let mut store = Store::default();
+
+    let a = store.insert(&Expr::parse("((a:b:a c:c) d:d)").unwrap());
+    let b = store.insert(&Expr::parse("(a:b:a c:c d:d)").unwrap());
+    assert_eq!(a, b);
+
+    // c:c and d:d are the same node
+    assert_eq!(store.len(), 7);
+    assert_eq!(store.size(a), 9);
+    assert!(store.is_closed(a));
+
+    let Node::App(function, _) = *store.node(a) else {
+        panic!()
+    };
+    let i = store.beta(function).unwrap();
+    assert_eq!(store.to_expr(i), Expr::parse("a:b:b").unwrap());
+
+    let inner = store.insert(&Expr::Group(vec![Expr::Relative(2), Expr::Relative(0)]));
+    assert_eq!(store.bound(inner), 3);
+    let lam = store.add(Node::Lam(inner));
+    assert_eq!(store.bound(lam), 2);
+}
+
+#[test]
+fn substitution() {
+    let mut store = Store::default();
+
+    // a:(b:c:(b c) a) is a:c:(a c)
+    let expr = Expr::parse("a:((b:c:(b c)) a)").unwrap();
+    let id = store.insert(&expr);
+    let Node::Lam(body) = *store.node(id) else {
+        panic!()
+    };
+    let reduced = store.beta(body).unwrap();
+    let lam = store.add(Node::Lam(reduced));
+    assert_eq!(lam, store.insert(&Expr::parse("a:c:(a c)").unwrap()));
+    assert!(store.to_expr(lam).same(&crate::nbe::normalize(&expr)));
+}
@@ -0,0 +1,280 @@
+// Explicit substitutions
+//
+// The λυ calculus of Lescanne, a beta reduction does not substitute, it makes a closure `a[s]`
+// and the substitution is moved into the term one step at a time. The variables are only shifted
+// when a substitution reaches them, so nothing is shifted that is not used.
+//
+// a/   replaces the variable 0 with a
+// ⇑s   the substitution s under a function
+// ↑    shifts the variables by one
+//
+// Beta      (λ a) b      = a[b/]
+// App       (a b)[s]     = a[s] b[s]
+// Abs       (λ a)[s]     = λ a[⇑s]
+// FVar      0[a/]        = a
+// RVar      n+1[a/]      = n
+// FVarLift  0[⇑s]        = 0
+// RVarLift  n+1[⇑s]      = n[s][↑]
+// VarShift  n[↑]         = n+1
+// Const     'a[s]        = 'a
+//
+// In `a[s][t]` the inner closure is moved first. A beta reduction is only made when all the
+// substitutions are done, otherwise the closures that are not done are copied with the arguments
+// and the terms grow very fast.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    Beta,
+    App,
+    Abs,
+    FVar,
+    RVar,
+    FVarLift,
+    RVarLift,
+    VarShift,
+    Const,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Beta => "Beta",
+            Rule::App => "App",
+            Rule::Abs => "Abs",
+            Rule::FVar => "FVar",
+            Rule::RVar => "RVar",
+            Rule::FVarLift => "FVarLift",
+            Rule::RVarLift => "RVarLift",
+            Rule::VarShift => "VarShift",
+            Rule::Const => "Const",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    App(Box<Term>, Box<Term>),
+    Lam(Box<Term>),
+    Var(u32),
+    Label(String),
+    /// `()`
+    Empty,
+    /// The term with the substitution that is not done yet.
+    Clos(Box<Term>, Subst),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subst {
+    Slash(Box<Term>),
+    Lift(Box<Subst>),
+    Shift,
+}
+
+impl Term {
+    pub fn from_expr(expr: &Expr) -> Term {
+        match expr {
+            Expr::Group(exprs) => {
+                let Some((first, rest)) = exprs.split_first() else {
+                    return Term::Empty;
+                };
+                rest.iter().fold(Term::from_expr(first), |term, expr| {
+                    Term::App(Box::new(term), Box::new(Term::from_expr(expr)))
+                })
+            }
+            Expr::Def(body) => Term::Lam(Box::new(Term::from_expr(body))),
+            Expr::Relative(id) => Term::Var(*id),
+            Expr::Label(name) => Term::Label(name.clone()),
+        }
+    }
+
+    /// Is `None` when a substitution is not done yet.
+    pub fn to_expr(&self) -> Option<Expr> {
+        Some(match self {
+            Term::App(function, argument) => {
+                let mut exprs = match function.to_expr()? {
+                    Expr::Group(exprs) if exprs.len() > 1 => exprs,
+                    function => vec![function],
+                };
+                exprs.push(argument.to_expr()?);
+                Expr::Group(exprs)
+            }
+            Term::Lam(body) => Expr::Def(Box::new(body.to_expr()?)),
+            Term::Var(id) => Expr::Relative(*id),
+            Term::Label(name) => Expr::Label(name.clone()),
+            Term::Empty => Expr::Group(Vec::default()),
+            Term::Clos(..) => return None,
+        })
+    }
+
+    pub fn format(&self) -> String {
+        match self {
+            Term::App(function, argument) => {
+                let function = match function.as_ref() {
+                    Term::Lam(_) => format!("({})", function.format()),
+                    _ => function.format(),
+                };
+                match argument.as_ref() {
+                    Term::App(..) | Term::Lam(_) => format!("{function} ({})", argument.format()),
+                    _ => format!("{function} {}", argument.format()),
+                }
+            }
+            Term::Lam(body) => format!("λ {}", body.format()),
+            Term::Var(id) => id.to_string(),
+            Term::Label(name) => format!("'{name}"),
+            Term::Empty => String::from("()"),
+            Term::Clos(term, subst) => match term.as_ref() {
+                Term::App(..) | Term::Lam(_) => format!("({})[{}]", term.format(), subst.format()),
+                _ => format!("{}[{}]", term.format(), subst.format()),
+            },
+        }
+    }
+
+    /// Performs the leftmost outermost step, returns the rule that was used.
+    pub fn step(&mut self) -> Option<Rule> {
+        self.push_step().or_else(|| self.beta_step())
+    }
+
+    fn push_step(&mut self) -> Option<Rule> {
+        match self {
+            Term::Clos(..) => {
+                let Term::Clos(term, subst) = std::mem::replace(self, Term::Empty) else {
+                    unreachable!()
+                };
+                let (term, rule) = push(*term, subst);
+                *self = term;
+                Some(rule)
+            }
+            Term::App(function, argument) => function.push_step().or_else(|| argument.push_step()),
+            Term::Lam(body) => body.push_step(),
+            Term::Var(_) | Term::Label(_) | Term::Empty => None,
+        }
+    }
+
+    fn beta_step(&mut self) -> Option<Rule> {
+        match self {
+            Term::App(function, _) if matches!(function.as_ref(), Term::Lam(_)) => {
+                let Term::App(function, argument) = std::mem::replace(self, Term::Empty) else {
+                    unreachable!()
+                };
+                let Term::Lam(body) = *function else {
+                    unreachable!()
+                };
+                *self = Term::Clos(body, Subst::Slash(argument));
+                Some(Rule::Beta)
+            }
+            Term::App(function, argument) => function.beta_step().or_else(|| argument.beta_step()),
+            Term::Lam(body) => body.beta_step(),
+            Term::Var(_) | Term::Label(_) | Term::Empty | Term::Clos(..) => None,
+        }
+    }
+}
+
+/// One step of `term[subst]`.
+fn push(term: Term, subst: Subst) -> (Term, Rule) {
+    match (term, subst) {
+        (Term::Clos(term, inner), subst) => {
+            let (term, rule) = push(*term, inner);
+            (Term::Clos(Box::new(term), subst), rule)
+        }
+        (Term::App(function, argument), subst) => (
+            Term::App(
+                Box::new(Term::Clos(function, subst.clone())),
+                Box::new(Term::Clos(argument, subst)),
+            ),
+            Rule::App,
+        ),
+        (Term::Lam(body), subst) => (
+            Term::Lam(Box::new(Term::Clos(body, Subst::Lift(Box::new(subst))))),
+            Rule::Abs,
+        ),
+        (term @ (Term::Label(_) | Term::Empty), _) => (term, Rule::Const),
+        (Term::Var(0), Subst::Slash(value)) => (*value, Rule::FVar),
+        (Term::Var(id), Subst::Slash(_)) => (Term::Var(id - 1), Rule::RVar),
+        (Term::Var(0), Subst::Lift(_)) => (Term::Var(0), Rule::FVarLift),
+        (Term::Var(id), Subst::Lift(subst)) => (
+            Term::Clos(
+                Box::new(Term::Clos(Box::new(Term::Var(id - 1)), *subst)),
+                Subst::Shift,
+            ),
+            Rule::RVarLift,
+        ),
+        (Term::Var(id), Subst::Shift) => (Term::Var(id + 1), Rule::VarShift),
+    }
+}
+
+impl Subst {
+    pub fn format(&self) -> String {
+        match self {
+            Subst::Slash(term) => match term.as_ref() {
+                Term::App(..) | Term::Lam(_) => format!("({})/", term.format()),
+                _ => format!("{}/", term.format()),
+            },
+            Subst::Lift(subst) => match subst.as_ref() {
+                Subst::Slash(_) => format!("⇑({})", subst.format()),
+                _ => format!("⇑{}", subst.format()),
+            },
+            Subst::Shift => String::from("↑"),
+        }
+    }
+}
+
+/// The beta normal form and how many steps were made, does not return if the expression has none.
+pub fn normalize(expr: &Expr) -> (Expr, usize) {
+    let mut term = Term::from_expr(expr);
+    let mut steps = 0;
+    while term.step().is_some() {
+        steps += 1;
+    }
+    (term.to_expr().expect("No substitution is left"), steps)
+}
+
+#[test]
+fn steps() {
+    let mut term = Term::from_expr(&Expr::parse("((a:b:a) 'X)").unwrap());
+    assert_eq!(term.format(), "(λ λ 1) 'X");
+
+    let mut rules = Vec::default();
+    let mut terms = Vec::default();
+    while let Some(rule) = term.step() {
+        rules.push(rule);
+        terms.push(term.format());
+    }
+    assert_eq!(
+        rules,
+        [
+            Rule::Beta,
+            Rule::Abs,
+            Rule::RVarLift,
+            Rule::FVar,
+            Rule::Const
+        ]
+    );
+    assert_eq!(
+        terms,
+        [
+            "(λ 1)['X/]",
+            "λ 1[⇑('X/)]",
+            "λ 0['X/][↑]",
+            "λ 'X[↑]",
+            "λ 'X"
+        ]
+    );
+}
+
+#[test]
+fn same_as_nbe() {
+    for text in [
+        // add 1 2
+        "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))",
+        // sub 10 6
+        "((m:n:(n(n:f:x:(n(g:h:(h(g f)))(u:x)(u:u)))m)) (f:x:(f(f(f(f(f(f(f(f(f(f x))))))))))) (f:x:(f(f(f(f(f(f x))))))))",
+        "((a:b:b) ((a:(a a)) (a:(a a))))",
+        "(x:((y:z:(y z)) x 'X))",
+        "(a:b:((c:d:(c d b)) a))",
+    ] {
+        let expr = Expr::parse(text).unwrap();
+        assert!(normalize(&expr).0.same(&crate::nbe::normalize(&expr)));
+    }
+}
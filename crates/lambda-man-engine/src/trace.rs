@@ -0,0 +1,250 @@
+// Traces
+//
+// A trace records the steps of a reduction, the term before and after each step and what was done.
+// It can be moved to any of its terms, back with `undo` and forward with `redo`, and a step that is
+// made when it is not at its end replaces the steps after it.
+//
+// `step` is the step of the REPL: a primitive, a Church shortcut, or the beta reduction that the
+// strategy of the trace chooses.
+
+use crate::reduce::Strategy;
+use crate::{Expr, church, prim};
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// The redexes that were found and the one that was reduced.
+    Beta {
+        candidates: Vec<(u32, Vec<usize>)>,
+        chosen: usize,
+    },
+    Primitive(prim::Call),
+    Shortcut(church::Shortcut),
+}
+
+impl Action {
+    /// The path of the redex of a beta reduction.
+    pub fn at(&self) -> Option<&[usize]> {
+        match self {
+            Action::Beta { candidates, chosen } => Some(&candidates[*chosen].1),
+            Action::Primitive(_) | Action::Shortcut(_) => None,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        match self {
+            Action::Beta { candidates, chosen } => {
+                format!("beta {} of {}", chosen + 1, candidates.len())
+            }
+            Action::Primitive(call) => call.format(),
+            Action::Shortcut(shortcut) => shortcut.format(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub before: Expr,
+    pub action: Action,
+    pub after: Expr,
+}
+
+/// How two traces are alike.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// The number of steps from the start that give the same terms.
+    pub common: usize,
+    pub lengths: (usize, usize),
+    /// The last terms are the same.
+    pub same_result: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub strategy: Strategy,
+    start: Expr,
+    steps: Vec<Step>,
+    /// The number of steps to the current term.
+    position: usize,
+}
+
+impl Trace {
+    pub fn new(start: Expr) -> Trace {
+        Trace {
+            strategy: Strategy::default(),
+            start,
+            steps: Vec::default(),
+            position: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The term after `n` steps.
+    pub fn term(&self, n: usize) -> Option<&Expr> {
+        match n {
+            0 => Some(&self.start),
+            n => self.steps.get(n - 1).map(|step| &step.after),
+        }
+    }
+
+    pub fn current(&self) -> &Expr {
+        self.term(self.position).unwrap()
+    }
+
+    pub fn last(&self) -> &Expr {
+        self.term(self.len()).unwrap()
+    }
+
+    pub fn seek(&mut self, n: usize) -> Option<&Expr> {
+        if n > self.len() {
+            return None;
+        }
+        self.position = n;
+        Some(self.current())
+    }
+
+    pub fn undo(&mut self) -> Option<&Expr> {
+        self.seek(self.position.checked_sub(1)?)
+    }
+
+    pub fn redo(&mut self) -> Option<&Expr> {
+        self.seek(self.position + 1)
+    }
+
+    /// Adds a step after the current term, the steps after it are removed.
+    pub fn record(&mut self, before: Expr, action: Action, after: Expr) {
+        self.steps.truncate(self.position);
+        self.steps.push(Step {
+            before,
+            action,
+            after,
+        });
+        self.position += 1;
+    }
+
+    /// Does the next step from the current term, `None` when it is in normal form, or a numeral
+    /// with the shortcuts.
+    pub fn step(
+        &mut self,
+        primitives: Option<&prim::Primitives>,
+        shortcuts: bool,
+    ) -> Option<&Step> {
+        let mut expr = self.current().clone();
+        expr.simplify();
+        let before = expr.clone();
+
+        let action = if let Some(call) =
+            primitives.and_then(|primitives| primitives.call(&mut expr))
+        {
+            Action::Primitive(call)
+        } else if let Some(shortcut) = shortcuts.then(|| church::shortcut(&mut expr)).flatten() {
+            Action::Shortcut(shortcut)
+        } else if shortcuts && church::numeral(&expr).is_some() {
+            return None;
+        } else {
+            let candidates = expr.find_beta_reductions();
+            let chosen = self.strategy.choose(&candidates)?;
+            if !expr.beta_reduction_at(&candidates[chosen].1) {
+                return None;
+            }
+            Action::Beta { candidates, chosen }
+        };
+
+        self.record(before, action, expr);
+        self.steps.last()
+    }
+
+    /// Does the recorded steps again from the start, returns the first step that does not give
+    /// the recorded term.
+    pub fn replay(&self, primitives: Option<&prim::Primitives>) -> Option<usize> {
+        let mut expr = self.start.clone();
+        for (i, step) in self.steps.iter().enumerate() {
+            expr.simplify();
+            let done = match &step.action {
+                Action::Beta { .. } => expr.beta_reduction_at(step.action.at().unwrap()),
+                Action::Primitive(call) => primitives
+                    .and_then(|primitives| primitives.call(&mut expr))
+                    .is_some_and(|again| again.name == call.name),
+                Action::Shortcut(shortcut) => church::shortcut(&mut expr) == Some(*shortcut),
+            };
+            if !done || expr != step.after {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    pub fn compare(&self, other: &Trace) -> Comparison {
+        // The steps from different terms are not common even when they give the same term
+        let same = |n| self.term(n).unwrap().same(other.term(n).unwrap());
+        let common = match same(0) {
+            true => (1..=self.len().min(other.len()))
+                .take_while(|n| same(*n))
+                .count(),
+            false => 0,
+        };
+        Comparison {
+            common,
+            lengths: (self.len(), other.len()),
+            same_result: self.last().same(other.last()),
+        }
+    }
+}
+
+#[test]
+fn history() {
+    // add 1 2
+    let text = "(a:b:(a c:d:e:(d(c d e))b) (a:b:(a b)) (a:b:(a(a b))))";
+    let mut trace = Trace::new(Expr::parse(text).unwrap());
+    while trace.step(None, false).is_some() {}
+    assert!(!trace.is_empty());
+    assert_eq!(church::numeral(trace.current()), Some(3));
+    assert_eq!(trace.replay(None), None);
+
+    let len = trace.len();
+    assert!(trace.undo().is_some());
+    assert_eq!(trace.position(), len - 1);
+    assert!(trace.redo().is_some());
+    assert!(trace.redo().is_none());
+
+    // a step from the middle replaces the rest
+    trace.seek(1).unwrap();
+    trace.step(None, false).unwrap();
+    assert_eq!(trace.len(), 2);
+    assert!(trace.seek(3).is_none());
+
+    let mut shortcut = Trace::new(Expr::parse(text).unwrap());
+    let step = shortcut.step(None, true).unwrap();
+    assert_eq!(step.action.format(), "add 1 2 = 3");
+    assert!(shortcut.step(None, true).is_none());
+
+    let mut full = Trace::new(Expr::parse(text).unwrap());
+    while full.step(None, false).is_some() {}
+    let comparison = shortcut.compare(&full);
+    assert_eq!(comparison.common, 0);
+    assert!(comparison.same_result);
+    assert_eq!(full.compare(&full.clone()).common, full.len());
+
+    // both are 'A after a step
+    let mut a = Trace::new(Expr::parse("((x:x) 'A)").unwrap());
+    let mut b = Trace::new(Expr::parse("((x:'A) 'B)").unwrap());
+    while a.step(None, false).is_some() {}
+    while b.step(None, false).is_some() {}
+    let comparison = a.compare(&b);
+    assert_eq!(comparison.common, 0);
+    assert!(comparison.same_result);
+}
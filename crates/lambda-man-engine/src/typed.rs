@@ -0,0 +1,653 @@
+// Explicitly typed lambda calculi
+//
+// The simply typed lambda calculus, System F and the Calculus of Constructions are checked as
+// [pure type systems](https://en.wikipedia.org/wiki/Pure_type_system) that allow different
+// products, `*` is the sort of types and `□` is the sort of `*`.
+//
+// \x:A. b           is a function
+// forall x:A. B     is the type of a function, ∀ and Π can be used too
+// A -> B            is forall x:A. B when B does not use x
+// 'Name             is a type of sort *
+//
+// \f:'A -> 'A. \x:'A. f (f x)                   is 2 in the simply typed lambda calculus
+// \A:*. \f:A -> A. \x:A. f (f x)                is 2 in System F
+// \A:*. \P:A -> *. \x:A. \p:P x. p              needs the Calculus of Constructions
+//
+// After checking, the types are erased and the rest is an untyped `Expr`.
+
+use crate::Expr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    /// `*`
+    Type,
+    /// `□`
+    Kind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Sort(Sort),
+    Var(u32),
+    /// A type of sort `*` that has no definition.
+    Const(String),
+    Pi(String, Box<Term>, Box<Term>),
+    Lam(String, Box<Term>, Box<Term>),
+    App(Box<Term>, Box<Term>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum System {
+    /// Only functions from terms to terms.
+    Stlc,
+    /// Functions from types to terms.
+    SystemF,
+    /// Functions from terms to types and from types to types.
+    Coc,
+}
+
+impl System {
+    /// Can a function from something of sort `from` to something of sort `to` exist.
+    pub fn allows(self, from: Sort, to: Sort) -> bool {
+        matches!(
+            (self, from, to),
+            (_, Sort::Type, Sort::Type)
+                | (System::SystemF, Sort::Kind, Sort::Type)
+                | (System::Coc, _, _)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckError {
+    /// `□` is the top, it has no type.
+    Kind,
+    /// A variable that is not bound by a function or a product.
+    Unbound(u32),
+    /// The type of a binder is not a type.
+    NotAType(String),
+    NotAFunction(String),
+    Mismatch {
+        expected: String,
+        found: String,
+    },
+    /// The system does not allow this product.
+    Rule(Sort, Sort),
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sort = |sort: &Sort| match sort {
+            Sort::Type => "*",
+            Sort::Kind => "□",
+        };
+
+        match self {
+            CheckError::Kind => write!(f, "`□` has no type"),
+            CheckError::Unbound(id) => write!(f, "Variable {id} is not bound"),
+            CheckError::NotAType(term) => write!(f, "`{term}` is not a type"),
+            CheckError::NotAFunction(term) => write!(f, "`{term}` is not a function"),
+            CheckError::Mismatch { expected, found } => {
+                write!(f, "Expected `{expected}` but found `{found}`")
+            }
+            CheckError::Rule(from, to) => write!(
+                f,
+                "Functions from `{}` to `{}` are not allowed in this system",
+                sort(from),
+                sort(to)
+            ),
+        }
+    }
+}
+
+impl Term {
+    fn app(function: Term, argument: Term) -> Term {
+        Term::App(Box::new(function), Box::new(argument))
+    }
+
+    pub fn format(&self) -> String {
+        self._format(&mut Vec::default())
+    }
+
+    fn _format(&self, names: &mut Vec<String>) -> String {
+        match self {
+            Term::Sort(Sort::Type) => String::from("*"),
+            Term::Sort(Sort::Kind) => String::from("□"),
+            Term::Var(id) => match names.len().checked_sub(*id as usize + 1) {
+                Some(i) => names[i].clone(),
+                None => format!("!{id}!"),
+            },
+            Term::Const(name) => format!("'{name}"),
+            Term::Pi(name, ty, body) => {
+                let ty_text = ty._format(names);
+                names.push(name.clone());
+                let body_text = body._format(names);
+                names.pop();
+
+                if body.uses(0) {
+                    format!("forall {name}:{ty_text}. {body_text}")
+                } else if let Term::Pi(..) | Term::Lam(..) = ty.as_ref() {
+                    format!("({ty_text}) -> {body_text}")
+                } else {
+                    format!("{ty_text} -> {body_text}")
+                }
+            }
+            Term::Lam(name, ty, body) => {
+                let ty = ty._format(names);
+                names.push(name.clone());
+                let body = body._format(names);
+                names.pop();
+                format!("\\{name}:{ty}. {body}")
+            }
+            Term::App(function, argument) => {
+                let function_text = function._format(names);
+                let function_text = match function.as_ref() {
+                    Term::Pi(..) | Term::Lam(..) => format!("({function_text})"),
+                    _ => function_text,
+                };
+                let argument_text = argument._format(names);
+                match argument.as_ref() {
+                    Term::Pi(..) | Term::Lam(..) | Term::App(..) => {
+                        format!("{function_text} ({argument_text})")
+                    }
+                    _ => format!("{function_text} {argument_text}"),
+                }
+            }
+        }
+    }
+
+    fn uses(&self, at: u32) -> bool {
+        match self {
+            Term::Var(id) => *id == at,
+            Term::Pi(_, ty, body) | Term::Lam(_, ty, body) => ty.uses(at) || body.uses(at + 1),
+            Term::App(function, argument) => function.uses(at) || argument.uses(at),
+            Term::Sort(_) | Term::Const(_) => false,
+        }
+    }
+
+    /// Adds `by` to every variable that is not bound inside of the term.
+    fn shift(&self, by: i64, depth: u32) -> Term {
+        match self {
+            Term::Var(id) if *id >= depth => Term::Var((*id as i64 + by) as u32),
+            Term::Pi(name, ty, body) => Term::Pi(
+                name.clone(),
+                Box::new(ty.shift(by, depth)),
+                Box::new(body.shift(by, depth + 1)),
+            ),
+            Term::Lam(name, ty, body) => Term::Lam(
+                name.clone(),
+                Box::new(ty.shift(by, depth)),
+                Box::new(body.shift(by, depth + 1)),
+            ),
+            Term::App(function, argument) => {
+                Term::app(function.shift(by, depth), argument.shift(by, depth))
+            }
+            term => term.clone(),
+        }
+    }
+
+    /// Replaces the variable `depth` with `value` and removes it.
+    fn subst(&self, depth: u32, value: &Term) -> Term {
+        match self {
+            Term::Var(id) if *id == depth => value.shift(depth as i64, 0),
+            Term::Var(id) if *id > depth => Term::Var(id - 1),
+            Term::Pi(name, ty, body) => Term::Pi(
+                name.clone(),
+                Box::new(ty.subst(depth, value)),
+                Box::new(body.subst(depth + 1, value)),
+            ),
+            Term::Lam(name, ty, body) => Term::Lam(
+                name.clone(),
+                Box::new(ty.subst(depth, value)),
+                Box::new(body.subst(depth + 1, value)),
+            ),
+            Term::App(function, argument) => {
+                Term::app(function.subst(depth, value), argument.subst(depth, value))
+            }
+            term => term.clone(),
+        }
+    }
+
+    /// The beta normal form, terms that are well typed always have one.
+    pub fn normalize(&self) -> Term {
+        match self {
+            Term::App(function, argument) => match function.normalize() {
+                Term::Lam(_, _, body) => body.subst(0, argument).normalize(),
+                function => Term::app(function, argument.normalize()),
+            },
+            Term::Pi(name, ty, body) => Term::Pi(
+                name.clone(),
+                Box::new(ty.normalize()),
+                Box::new(body.normalize()),
+            ),
+            Term::Lam(name, ty, body) => Term::Lam(
+                name.clone(),
+                Box::new(ty.normalize()),
+                Box::new(body.normalize()),
+            ),
+            term => term.clone(),
+        }
+    }
+
+    /// Compares terms without the names of the binders.
+    fn same(&self, other: &Term) -> bool {
+        match (self, other) {
+            (Term::Pi(_, a_ty, a_body), Term::Pi(_, b_ty, b_body))
+            | (Term::Lam(_, a_ty, a_body), Term::Lam(_, b_ty, b_body)) => {
+                a_ty.same(b_ty) && a_body.same(b_body)
+            }
+            (Term::App(a_function, a_argument), Term::App(b_function, b_argument)) => {
+                a_function.same(b_function) && a_argument.same(b_argument)
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+struct Checker {
+    system: System,
+    /// The names and the types of the variables, the last one is `0`.
+    context: Vec<(String, Term)>,
+}
+
+impl Checker {
+    fn format(&self, term: &Term) -> String {
+        let mut names = self.context.iter().map(|(name, _)| name.clone()).collect();
+        term._format(&mut names)
+    }
+
+    fn sort(&mut self, term: &Term) -> Result<Sort, CheckError> {
+        match self.infer(term)?.normalize() {
+            Term::Sort(sort) => Ok(sort),
+            _ => Err(CheckError::NotAType(self.format(term))),
+        }
+    }
+
+    fn infer(&mut self, term: &Term) -> Result<Term, CheckError> {
+        match term {
+            Term::Sort(Sort::Type) => Ok(Term::Sort(Sort::Kind)),
+            Term::Sort(Sort::Kind) => Err(CheckError::Kind),
+            Term::Var(id) => {
+                let Some(i) = self.context.len().checked_sub(*id as usize + 1) else {
+                    return Err(CheckError::Unbound(*id));
+                };
+                let (_, ty) = &self.context[i];
+                Ok(ty.shift(*id as i64 + 1, 0))
+            }
+            Term::Const(_) => Ok(Term::Sort(Sort::Type)),
+            Term::Pi(name, ty, body) => {
+                let from = self.sort(ty)?;
+                self.context.push((name.clone(), *ty.clone()));
+                let to = self.sort(body);
+                self.context.pop();
+                let to = to?;
+
+                if !self.system.allows(from, to) {
+                    return Err(CheckError::Rule(from, to));
+                }
+
+                Ok(Term::Sort(to))
+            }
+            Term::Lam(name, ty, body) => {
+                self.sort(ty)?;
+                self.context.push((name.clone(), *ty.clone()));
+                let body = self.infer(body);
+                self.context.pop();
+
+                let pi = Term::Pi(name.clone(), ty.clone(), Box::new(body?));
+                self.infer(&pi)?;
+                Ok(pi)
+            }
+            Term::App(function, argument) => {
+                let Term::Pi(_, ty, body) = self.infer(function)?.normalize() else {
+                    return Err(CheckError::NotAFunction(self.format(function)));
+                };
+
+                let found = self.infer(argument)?.normalize();
+                if !found.same(&ty) {
+                    return Err(CheckError::Mismatch {
+                        expected: self.format(&ty),
+                        found: self.format(&found),
+                    });
+                }
+
+                Ok(body.subst(0, argument))
+            }
+        }
+    }
+
+    /// Types, kinds and functions that return types are not needed after checking.
+    fn is_erased(&mut self, ty: &Term) -> bool {
+        match ty.normalize() {
+            Term::Sort(_) => true,
+            ty => matches!(
+                self.infer(&ty).map(|ty| ty.normalize()),
+                Ok(Term::Sort(Sort::Kind))
+            ),
+        }
+    }
+
+    /// `erased` says which variables of the context are removed.
+    fn erase(&mut self, term: &Term, erased: &mut Vec<bool>) -> Option<Expr> {
+        match term {
+            Term::Var(id) => {
+                // a type that is removed
+                if erased.iter().rev().nth(*id as usize) != Some(&false) {
+                    return None;
+                }
+                let skipped = erased
+                    .iter()
+                    .rev()
+                    .take(*id as usize)
+                    .filter(|e| **e)
+                    .count();
+                Some(Expr::Relative(*id - skipped as u32))
+            }
+            Term::Lam(name, ty, body) => {
+                let is_erased = self.is_erased(ty);
+                self.context.push((name.clone(), *ty.clone()));
+                erased.push(is_erased);
+                let body = self.erase(body, erased);
+                erased.pop();
+                self.context.pop();
+
+                if is_erased {
+                    body
+                } else {
+                    Some(Expr::Def(Box::new(body?)))
+                }
+            }
+            Term::App(function, argument) => {
+                let function = self.erase(function, erased)?;
+                let ty = self.infer(argument).ok()?;
+                if self.is_erased(&ty) {
+                    return Some(function);
+                }
+
+                let argument = self.erase(argument, erased)?;
+                Some(match function {
+                    Expr::Group(mut exprs) if exprs.len() > 1 => {
+                        exprs.push(argument);
+                        Expr::Group(exprs)
+                    }
+                    function => Expr::Group(vec![function, argument]),
+                })
+            }
+            Term::Sort(_) | Term::Const(_) | Term::Pi(..) => None,
+        }
+    }
+}
+
+/// Checks the term and returns the normal form of its type.
+pub fn check(term: &Term, system: System) -> Result<Term, CheckError> {
+    let mut checker = Checker {
+        system,
+        context: Vec::default(),
+    };
+    Ok(checker.infer(term)?.normalize())
+}
+
+/// Removes the types from a term that was checked, a term that is a type has nothing left.
+pub fn erase(term: &Term) -> Option<Expr> {
+    let mut checker = Checker {
+        system: System::Coc,
+        context: Vec::default(),
+    };
+    checker.erase(term, &mut Vec::default())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Const(String),
+    Lambda,
+    Forall,
+    Open,
+    Close,
+    Colon,
+    Dot,
+    Arrow,
+    Star,
+    Box,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut out = Vec::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '\\' | 'λ' => Token::Lambda,
+            '∀' | 'Π' => Token::Forall,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Colon,
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '□' => Token::Box,
+            '-' if chars.next_if_eq(&'>').is_some() => Token::Arrow,
+            '\'' | '_' | 'a'..='z' | 'A'..='Z' => {
+                let mut name = String::default();
+                if ch != '\'' {
+                    name.push(ch);
+                }
+                while let Some(ch) = chars.next_if(|ch| ch.is_alphanumeric() || *ch == '_') {
+                    name.push(ch);
+                }
+
+                match (ch, name.as_str()) {
+                    ('\'', _) => Token::Const(name),
+                    (_, "forall") => Token::Forall,
+                    _ => Token::Ident(name),
+                }
+            }
+            ch => {
+                eprintln!("Unexpected `{ch}`");
+                return None;
+            }
+        };
+        out.push(token);
+    }
+
+    Some(out)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    i: usize,
+    scope: Vec<String>,
+}
+
+impl Parser {
+    fn next_if(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.i) == Some(token);
+        if found {
+            self.i += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        if self.next_if(&token) {
+            Some(())
+        } else {
+            eprintln!("Expected {token:?} at token {}", self.i);
+            None
+        }
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        if let Some(Token::Ident(name)) = self.tokens.get(self.i) {
+            self.i += 1;
+            Some(name.clone())
+        } else {
+            eprintln!("Expected a name at token {}", self.i);
+            None
+        }
+    }
+
+    fn term(&mut self) -> Option<Term> {
+        let lambda = self.next_if(&Token::Lambda);
+        if lambda || self.next_if(&Token::Forall) {
+            let name = self.ident()?;
+            self.expect(Token::Colon)?;
+            let ty = self.term()?;
+            self.expect(Token::Dot)?;
+
+            self.scope.push(name.clone());
+            let body = self.term();
+            self.scope.pop();
+
+            let (ty, body) = (Box::new(ty), Box::new(body?));
+            return Some(if lambda {
+                Term::Lam(name, ty, body)
+            } else {
+                Term::Pi(name, ty, body)
+            });
+        }
+
+        let mut out = self.atom()?;
+        while let Some(next) = self.tokens.get(self.i)
+            && matches!(
+                next,
+                Token::Ident(_) | Token::Const(_) | Token::Open | Token::Star | Token::Box
+            )
+        {
+            out = Term::app(out, self.atom()?);
+        }
+
+        if self.next_if(&Token::Arrow) {
+            self.scope.push(String::default());
+            let to = self.term();
+            self.scope.pop();
+            return Some(Term::Pi(String::from("_"), Box::new(out), Box::new(to?)));
+        }
+
+        Some(out)
+    }
+
+    fn atom(&mut self) -> Option<Term> {
+        let token = self.tokens.get(self.i).cloned();
+        self.i += 1;
+
+        match token? {
+            Token::Ident(name) => {
+                let Some(id) = self.scope.iter().rev().position(|n| *n == name) else {
+                    eprintln!("Cannot find name `{name}` in {:?}", self.scope);
+                    return None;
+                };
+                Some(Term::Var(id as u32))
+            }
+            Token::Const(name) => Some(Term::Const(name)),
+            Token::Star => Some(Term::Sort(Sort::Type)),
+            Token::Box => Some(Term::Sort(Sort::Kind)),
+            Token::Open => {
+                let term = self.term()?;
+                self.expect(Token::Close)?;
+                Some(term)
+            }
+            token => {
+                eprintln!("Unexpected {token:?} at token {}", self.i - 1);
+                None
+            }
+        }
+    }
+}
+
+/// Parses a typed term, like `\A:*. \x:A. x`.
+pub fn parse(text: &str) -> Option<Term> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        i: 0,
+        scope: Vec::default(),
+    };
+    let term = parser.term()?;
+
+    if parser.i != parser.tokens.len() {
+        eprintln!(
+            "Unexpected {:?} at token {}",
+            parser.tokens[parser.i], parser.i
+        );
+        return None;
+    }
+
+    Some(term)
+}
+
+#[test]
+fn simply_typed() {
+    let two = parse("\\f:'A -> 'A. \\x:'A. f (f x)").unwrap();
+    assert_eq!(
+        check(&two, System::Stlc).unwrap().format(),
+        "('A -> 'A) -> 'A -> 'A"
+    );
+
+    let erased = erase(&two).unwrap();
+    assert_eq!(
+        crate::blc::encode(&erased),
+        crate::blc::encode(&Expr::from(2))
+    );
+
+    let error = check(&parse("\\x:'A. \\f:'B -> 'B. f x").unwrap(), System::Stlc).unwrap_err();
+    assert_eq!(error.to_string(), "Expected `'B` but found `'A`");
+
+    let id = parse("\\A:*. \\x:A. x").unwrap();
+    assert_eq!(
+        check(&id, System::Stlc),
+        Err(CheckError::Rule(Sort::Kind, Sort::Type))
+    );
+
+    assert_eq!(
+        check(&Term::Var(0), System::Stlc),
+        Err(CheckError::Unbound(0))
+    );
+}
+
+#[test]
+fn system_f() {
+    let id = parse("\\A:*. \\x:A. x").unwrap();
+    assert_eq!(
+        check(&id, System::SystemF).unwrap().format(),
+        "forall A:*. A -> A"
+    );
+
+    // id applied to its own type and to itself
+    let term =
+        parse("(\\id:(forall A:*. A -> A). id (forall A:*. A -> A) id) (\\A:*. \\x:A. x)").unwrap();
+    assert_eq!(
+        check(&term, System::SystemF).unwrap().format(),
+        "forall A:*. A -> A"
+    );
+
+    let erased = erase(&term).unwrap();
+    assert_eq!(erased.format(0), "(a:(a a) a:a)");
+
+    let two = parse("\\A:*. \\f:A -> A. \\x:A. f (f x)").unwrap();
+    assert_eq!(
+        check(&two, System::SystemF).unwrap().format(),
+        "forall A:*. (A -> A) -> A -> A"
+    );
+    assert_eq!(
+        crate::blc::encode(&erase(&two).unwrap()),
+        crate::blc::encode(&Expr::from(2))
+    );
+}
+
+#[test]
+fn calculus_of_constructions() {
+    let term = parse("\\A:*. \\P:A -> *. \\x:A. \\p:P x. p").unwrap();
+    assert_eq!(
+        check(&term, System::SystemF),
+        Err(CheckError::Rule(Sort::Type, Sort::Kind))
+    );
+    assert_eq!(
+        check(&term, System::Coc).unwrap().format(),
+        "forall A:*. forall P:A -> *. forall x:A. P x -> P x"
+    );
+    assert_eq!(erase(&term).unwrap().format(0), "a:b:b");
+
+    // the type of a type is erased
+    assert_eq!(erase(&parse("'A -> 'A").unwrap()), None);
+    assert_eq!(erase(&parse("\\A:*. A").unwrap()), None);
+}
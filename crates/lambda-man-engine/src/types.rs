@@ -0,0 +1,280 @@
+// Hindley–Milner type inference
+//
+// A group that starts with a definition, like `(a:(a a) b:b)`, is typed as `let a = b:b in (a a)`
+// so the argument can be used with different types.
+// Every label is a constant with one type, `'f` has the same type everywhere.
+
+use std::collections::HashMap;
+
+use crate::Expr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Var(u32),
+    Arrow(Box<Type>, Box<Type>),
+}
+
+impl Type {
+    pub fn arrow(from: Type, to: Type) -> Type {
+        Type::Arrow(Box::new(from), Box::new(to))
+    }
+
+    fn vars(&self, out: &mut Vec<u32>) {
+        match self {
+            Type::Var(id) => {
+                if !out.contains(id) {
+                    out.push(*id);
+                }
+            }
+            Type::Arrow(from, to) => {
+                from.vars(out);
+                to.vars(out);
+            }
+        }
+    }
+
+    /// Formats the type, the variables are named in the order that they are found.
+    pub fn format(&self) -> String {
+        let mut names = Vec::default();
+        self.vars(&mut names);
+        self._format(&names)
+    }
+
+    fn _format(&self, names: &[u32]) -> String {
+        match self {
+            Type::Var(id) => match names.iter().position(|name| name == id) {
+                Some(i) => type_name(i),
+                None => format!("?{id}"),
+            },
+            Type::Arrow(from, to) => {
+                if let Type::Arrow(..) = from.as_ref() {
+                    format!("({}) -> {}", from._format(names), to._format(names))
+                } else {
+                    format!("{} -> {}", from._format(names), to._format(names))
+                }
+            }
+        }
+    }
+}
+
+fn type_name(i: usize) -> String {
+    let ch = (b'a' + (i % 26) as u8) as char;
+    if i < 26 {
+        ch.to_string()
+    } else {
+        format!("{ch}{}", i / 26)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    /// The variable would have to contain itself.
+    Occurs(Type, Type),
+    EmptyGroup,
+    Unbound(u32),
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::Occurs(var, ty) => {
+                let mut names = Vec::default();
+                var.vars(&mut names);
+                ty.vars(&mut names);
+                write!(
+                    f,
+                    "Cannot construct the infinite type `{} = {}`",
+                    var._format(&names),
+                    ty._format(&names)
+                )
+            }
+            TypeError::EmptyGroup => write!(f, "Cannot type an empty group"),
+            TypeError::Unbound(id) => write!(f, "Variable `!{id}!` is not bound"),
+        }
+    }
+}
+
+/// A type that is generic over `vars`.
+#[derive(Debug, Clone)]
+struct Scheme {
+    vars: Vec<u32>,
+    ty: Type,
+}
+
+#[derive(Default)]
+struct Infer {
+    subst: Vec<Option<Type>>,
+    labels: HashMap<String, Type>,
+}
+
+impl Infer {
+    fn fresh(&mut self) -> Type {
+        self.subst.push(None);
+        Type::Var(self.subst.len() as u32 - 1)
+    }
+
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(id) => match &self.subst[*id as usize] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            Type::Arrow(from, to) => Type::arrow(self.resolve(from), self.resolve(to)),
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), TypeError> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(id), ty) | (ty, Type::Var(id)) => {
+                let mut vars = Vec::default();
+                ty.vars(&mut vars);
+                if vars.contains(&id) {
+                    return Err(TypeError::Occurs(Type::Var(id), ty));
+                }
+                self.subst[id as usize] = Some(ty);
+                Ok(())
+            }
+            (Type::Arrow(a_from, a_to), Type::Arrow(b_from, b_to)) => {
+                self.unify(&a_from, &b_from)?;
+                self.unify(&a_to, &b_to)
+            }
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let fresh = scheme
+            .vars
+            .iter()
+            .map(|var| (*var, self.fresh()))
+            .collect::<HashMap<u32, Type>>();
+
+        fn replace(ty: &Type, fresh: &HashMap<u32, Type>) -> Type {
+            match ty {
+                Type::Var(id) => fresh.get(id).cloned().unwrap_or(Type::Var(*id)),
+                Type::Arrow(from, to) => Type::arrow(replace(from, fresh), replace(to, fresh)),
+            }
+        }
+
+        replace(&scheme.ty, &fresh)
+    }
+
+    fn generalize(&self, env: &[Scheme], ty: &Type) -> Scheme {
+        let mut used = self.labels.values().cloned().collect::<Vec<Type>>();
+        used.extend(env.iter().map(|scheme| scheme.ty.clone()));
+
+        let mut env_vars = Vec::default();
+        for ty in used {
+            self.resolve(&ty).vars(&mut env_vars);
+        }
+
+        let ty = self.resolve(ty);
+        let mut vars = Vec::default();
+        ty.vars(&mut vars);
+        vars.retain(|var| !env_vars.contains(var));
+
+        Scheme { vars, ty }
+    }
+
+    fn infer(&mut self, env: &mut Vec<Scheme>, expr: &Expr) -> Result<Type, TypeError> {
+        match expr {
+            Expr::Group(exprs) => {
+                let (first, mut rest) = exprs.split_first().ok_or(TypeError::EmptyGroup)?;
+
+                let mut ty = match (first.unwrap(), rest.split_first()) {
+                    (Expr::Def(body), Some((value, after))) => {
+                        let value = self.infer(env, value)?;
+                        let scheme = self.generalize(env, &value);
+                        env.push(scheme);
+                        let ty = self.infer(env, body);
+                        env.pop();
+                        rest = after;
+                        ty?
+                    }
+                    _ => self.infer(env, first)?,
+                };
+
+                for arg in rest {
+                    let arg = self.infer(env, arg)?;
+                    let result = self.fresh();
+                    self.unify(&ty, &Type::arrow(arg, result.clone()))?;
+                    ty = result;
+                }
+
+                Ok(ty)
+            }
+            Expr::Def(body) => {
+                let arg = self.fresh();
+                env.push(Scheme {
+                    vars: Vec::default(),
+                    ty: arg.clone(),
+                });
+                let body = self.infer(env, body);
+                env.pop();
+                Ok(Type::arrow(arg, body?))
+            }
+            Expr::Relative(id) => {
+                let scheme = env
+                    .len()
+                    .checked_sub(*id as usize + 1)
+                    .map(|i| env[i].clone())
+                    .ok_or(TypeError::Unbound(*id))?;
+                Ok(self.instantiate(&scheme))
+            }
+            Expr::Label(name) => {
+                if let Some(ty) = self.labels.get(name) {
+                    return Ok(ty.clone());
+                }
+                let ty = self.fresh();
+                self.labels.insert(name.clone(), ty.clone());
+                Ok(ty)
+            }
+        }
+    }
+}
+
+/// Infers the principal type of the expression.
+pub fn infer(expr: &Expr) -> Result<Type, TypeError> {
+    let mut infer = Infer::default();
+    let ty = infer.infer(&mut Vec::default(), expr)?;
+    Ok(infer.resolve(&ty))
+}
+
+#[test]
+fn church_numerals() {
+    for (num, ty) in [
+        (0, "a -> b -> b"),
+        (1, "(a -> b) -> a -> b"),
+        (2, "(a -> a) -> a -> a"),
+        (3, "(a -> a) -> a -> a"),
+    ] {
+        assert_eq!(infer(&Expr::from(num)).unwrap().format(), ty);
+    }
+
+    // succ
+    let ty = infer(&Expr::parse("n:f:x:(f (n f x))").unwrap()).unwrap();
+    assert_eq!(ty.format(), "((a -> b) -> c -> a) -> (a -> b) -> c -> b");
+
+    // S
+    let ty = infer(&Expr::parse("a:b:c:(a c (b c))").unwrap()).unwrap();
+    assert_eq!(ty.format(), "(a -> b -> c) -> (a -> b) -> a -> c");
+}
+
+#[test]
+fn occurs_check() {
+    let error = infer(&Expr::parse("a:(a a)").unwrap()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Cannot construct the infinite type `a = a -> b`"
+    );
+
+    // let i = x:x in (i i)
+    let ty = infer(&Expr::parse("(i:(i i) x:x)").unwrap()).unwrap();
+    assert_eq!(ty.format(), "a -> a");
+
+    // let x = y:y in let i = x in (i i), both are generic
+    assert!(infer(&Expr::parse("(x:((i:(i i)) x) y:y)").unwrap()).is_ok());
+    assert!(infer(&Expr::parse("x:(x x)").unwrap()).is_err());
+    // f is the variable of a function so it is not generic, and neither is i that is f
+    assert!(infer(&Expr::parse("f:((i:(i i)) f)").unwrap()).is_err());
+}
@@ -1,11 +1,61 @@
+use std::sync::mpsc;
+
 use eframe::egui;
 
-use lambda_man_engine::Expr;
+use lambda_man_engine::{
+    Expr,
+    reduce::{self, Cancel, Reduction, Strategy},
+    trace::{Action, Trace},
+    typed,
+};
+
+/// Reduces on its own thread, so the UI is drawn while it runs.
+struct Worker {
+    cancel: Cancel,
+    /// The reductions that are not recorded yet, it waits when there are too many.
+    reductions: mpsc::Receiver<Reduction>,
+}
+
+impl Worker {
+    /// Reduces the term until the normal form or until it is stopped.
+    fn start(expr: Expr, strategy: Strategy, ctx: egui::Context) -> Worker {
+        let cancel = Cancel::new();
+        let (sender, reductions) = mpsc::sync_channel(256);
+
+        let stop = cancel.clone();
+        // Everything on `Expr` is recursive
+        let spawned = std::thread::Builder::new()
+            .stack_size(1 << 30)
+            .spawn(move || {
+                reduce::evaluate(&expr, strategy, &stop, |progress| {
+                    if sender.send(progress.reduction.clone()).is_err() {
+                        stop.cancel();
+                    }
+                    ctx.request_repaint();
+                });
+                ctx.request_repaint();
+            });
+        if spawned.is_err() {
+            cancel.cancel();
+        }
+
+        Worker { cancel, reductions }
+    }
+}
+
+/// The reductions that are recorded in a frame, the others are recorded in the next frames.
+const RECORDED_PER_FRAME: usize = 64;
 
 pub struct App {
-    expr: Expr,
+    /// The input and the steps that were made from it.
+    trace: Trace,
+    worker: Option<Worker>,
     input: String,
     debug: bool,
+    /// The input is typed and checked by this system.
+    system: Option<typed::System>,
+    /// The type of the input or why it cannot be typed.
+    info: String,
 
     scene_rect: egui::Rect,
     frame: usize,
@@ -16,14 +66,93 @@ impl Default for App {
         let expr = Expr::parse("a:b:b").unwrap();
         Self {
             input: expr.format(0),
-            expr,
+            trace: Trace::new(expr),
+            worker: None,
             debug: false,
+            system: None,
+            info: String::default(),
             scene_rect: egui::Rect::ZERO,
             frame: 0,
         }
     }
 }
 
+impl App {
+    fn set_expr(&mut self, expr: Expr) {
+        self.stop();
+        self.trace = Trace::new(expr);
+        self.scene_rect = egui::Rect::ZERO;
+        self.frame = 0;
+    }
+
+    /// The steps that the worker has made are kept, it makes one more at most after the cancel.
+    fn stop(&mut self) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        worker.cancel.cancel();
+        for reduction in worker.reductions.iter() {
+            self.record(reduction);
+        }
+    }
+
+    /// Records the steps that the worker has made, the worker is removed when it is done.
+    fn receive(&mut self, ctx: &egui::Context) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        for _ in 0..RECORDED_PER_FRAME {
+            match worker.reductions.try_recv() {
+                Ok(reduction) => self.record(reduction),
+                Err(mpsc::TryRecvError::Empty) => {
+                    self.worker = Some(worker);
+                    return;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => return,
+            }
+        }
+        // The others are recorded in the next frame
+        self.worker = Some(worker);
+        ctx.request_repaint();
+    }
+
+    /// A reduction of the current term, the path is in the simplified term like in a replay.
+    fn record(&mut self, reduction: Reduction) {
+        let action = Action::Beta {
+            candidates: reduction.candidates,
+            chosen: reduction.chosen,
+        };
+        self.trace
+            .record(self.trace.current().clone(), action, reduction.expr);
+    }
+
+    fn submit(&mut self) {
+        let Some(system) = self.system else {
+            if let Some(expr) = Expr::parse(&self.input) {
+                self.input = expr.format(0);
+                self.info = String::default();
+                self.set_expr(expr);
+            }
+            return;
+        };
+
+        let Some(term) = typed::parse(&self.input) else {
+            self.info = String::from("Cannot parse");
+            return;
+        };
+
+        match typed::check(&term, system) {
+            Ok(ty) => {
+                self.info = ty.format();
+                if let Some(expr) = typed::erase(&term) {
+                    self.set_expr(expr);
+                }
+            }
+            Err(error) => self.info = error.to_string(),
+        }
+    }
+}
+
 pub fn gen_rects(expr: &Expr, cursor: egui::Pos2) -> Vec<(bool, egui::Rect)> {
     let mut out = Vec::default();
 
@@ -114,7 +243,7 @@ pub fn gen_rects(expr: &Expr, cursor: egui::Pos2) -> Vec<(bool, egui::Rect)> {
                 egui::Rect::from_min_size(cursor - egui::vec2(0., offset), egui::vec2(20., offset)),
             ));
         }
-        Expr::Label(label) => {
+        Expr::Label(_) => {
             // I don't know how to show this.
 
             out.push((
@@ -138,24 +267,75 @@ pub fn gen_rects(expr: &Expr, cursor: egui::Pos2) -> Vec<(bool, egui::Rect)> {
 }
 
 impl eframe::App for App {
-    fn update(&mut self, ctx: &eframe::egui::Context, frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        self.receive(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.checkbox(&mut self.debug, "Debug");
+
+                let name = |system| match system {
+                    None => "Untyped",
+                    Some(typed::System::Stlc) => "STLC",
+                    Some(typed::System::SystemF) => "System F",
+                    Some(typed::System::Coc) => "CoC",
+                };
+                egui::ComboBox::from_id_salt("system")
+                    .selected_text(name(self.system))
+                    .show_ui(ui, |ui| {
+                        for system in [
+                            None,
+                            Some(typed::System::Stlc),
+                            Some(typed::System::SystemF),
+                            Some(typed::System::Coc),
+                        ] {
+                            ui.selectable_value(&mut self.system, system, name(system));
+                        }
+                    });
+
                 if ui.text_edit_singleline(&mut self.input).lost_focus()
                     && ui.input(|i| i.key_pressed(egui::Key::Enter))
                 {
-                    if let Some(expr) = Expr::parse(&self.input) {
-                        self.expr = expr;
-                        self.input = self.expr.format(0);
-                        self.scene_rect = egui::Rect::ZERO;
-                        self.frame = 0;
+                    self.submit();
+                }
+
+                ui.label(&self.info);
+            });
+
+            ui.horizontal(|ui| {
+                // The steps of the worker are recorded after the current term
+                let idle = self.worker.is_none();
+                if ui.add_enabled(idle, egui::Button::new("Undo")).clicked() {
+                    self.trace.undo();
+                }
+                if ui.add_enabled(idle, egui::Button::new("Redo")).clicked() {
+                    self.trace.redo();
+                }
+                if ui.add_enabled(idle, egui::Button::new("Step")).clicked() {
+                    self.trace.step(None, false);
+                }
+                if idle {
+                    if ui.button("Run").clicked() {
+                        let expr = self.trace.current().clone();
+                        let strategy = self.trace.strategy;
+                        self.worker = Some(Worker::start(expr, strategy, ctx.clone()));
                     }
+                } else if ui.button("Stop").clicked() {
+                    self.stop();
+                }
+                ui.label(format!(
+                    "{} of {} steps, size {}",
+                    self.trace.position(),
+                    self.trace.len(),
+                    self.trace.current().size()
+                ));
+                if !idle {
+                    ui.spinner();
                 }
             });
 
             egui::Scene::new().show(ui, &mut self.scene_rect, |ui| {
-                let rects = gen_rects(&self.expr, egui::pos2(60., 50.));
+                let rects = gen_rects(self.trace.current(), egui::pos2(60., 50.));
                 let len = rects.len();
 
                 let mut min = egui::pos2(0., 0.);